@@ -7,6 +7,9 @@ macro_rules! init_app_actions {
         app.set_accelerators_for_action::<OpenNewDir>(&["<ctrl><shift>o"]);
         app.set_accelerators_for_action::<OpenSFImg>(&["Up"]);
         app.set_accelerators_for_action::<OpenSF>(&["<ctrl>e"]);
+        app.set_accelerators_for_action::<OpenSearch>(&["<ctrl>f"]);
+        app.set_accelerators_for_action::<ToggleGallery>(&["<ctrl>g"]);
+        app.set_accelerators_for_action::<ToggleBrowser>(&["<ctrl>b"]);
 
         let next_sender = $sender.clone();
         let action_next: RelmAction<NextSFAction> =
@@ -28,12 +31,27 @@ macro_rules! init_app_actions {
         let action_open_sf: RelmAction<OpenSF> =
             RelmAction::new_stateless(move |_| open_sf_sender.input(AppInput::OpenDir));
 
+        let open_search_sender = $sender.clone();
+        let action_open_search: RelmAction<OpenSearch> =
+            RelmAction::new_stateless(move |_| open_search_sender.input(AppInput::OpenSearch));
+
+        let gallery_sender = $sender.clone();
+        let action_gallery: RelmAction<ToggleGallery> =
+            RelmAction::new_stateless(move |_| gallery_sender.input(AppInput::ToggleGallery));
+
+        let browser_sender = $sender.clone();
+        let action_browser: RelmAction<ToggleBrowser> =
+            RelmAction::new_stateless(move |_| browser_sender.input(AppInput::ToggleBrowser));
+
         let mut alone_group = RelmActionGroup::<ShortcutsActionGroup>::new();
         alone_group.add_action(action_next);
         alone_group.add_action(action_prev);
         alone_group.add_action(action_open);
         alone_group.add_action(action_open_sf_img);
         alone_group.add_action(action_open_sf);
+        alone_group.add_action(action_open_search);
+        alone_group.add_action(action_gallery);
+        alone_group.add_action(action_browser);
         alone_group.register_for_widget(&$widgets.main_window);
     };
 }
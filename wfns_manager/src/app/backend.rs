@@ -1,11 +1,23 @@
-use nanorand::{Rng, WyRand};
 use std::{
+    collections::{HashMap, HashSet},
+    env,
     fs::{self},
-    path::Path,
-    process::Command,
+    path::{Component, Path, PathBuf},
 };
 
-use crate::app::preferences::AppMode;
+use crate::app::{
+    cover,
+    deck::{SavedSession, SeenDeck},
+    error::AppError,
+    filter::Filter,
+    gallery::GalleryEntry,
+    library::{ItemMeta, Library},
+    preferences::AppMode,
+    scanner::{ScanOptions, ScannedItems},
+    search::{self, SearchHit},
+    shared_thumbs, thumb_cache, thumbnailer,
+    weighting::{self, PickDetails, PickMode, Weight},
+};
 
 macro_rules! tsuts {
     ($e:expr) => {
@@ -21,55 +33,89 @@ pub trait Openable {
     fn open_image(&self) -> bool;
 }
 
+pub trait DeckItem {
+    /// path of the item relative to its root folder (or its absolute path in a multi-root session), used to
+    /// recognize it across sessions
+    fn key(&self) -> &str;
+
+    /// root folder the item comes from
+    fn root(&self) -> &str;
+
+    /// number of elements inside of the item (e.g. the images of a subfolder), if it makes sense
+    fn count(&self) -> Option<usize> {
+        None
+    }
+
+    /// absolute path of the item
+    fn path(&self) -> String;
+
+    /// modification date of the item (unix timestamp), read by the scan
+    fn mtime(&self) -> Option<u64>;
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct SubFolder {
     pub name: String,
     root_path: String,
+    key: String,
     pub thumbnail: Option<String>,
+    pub images_count: usize,
+    pub mtime: Option<u64>,
 }
 
 impl SubFolder {
     pub fn new(name: &str, root_path: &str) -> Self {
         let path = format!("{}/{}", root_path, name);
+        let (thumbnail, images_count) = match Self::get_thumbnail(&path) {
+            Ok((t, c)) => (Some(t), c),
+            Err(_) => (None, 0),
+        };
         Self {
             name: name.to_string(),
             root_path: root_path.to_string(),
-            thumbnail: Self::get_thumbnail(&path).ok(),
+            key: name.to_string(),
+            thumbnail,
+            images_count,
+            mtime: None,
         }
     }
 
-    fn get_thumbnail(subpath: &str) -> Result<String, ()> {
-        let mut entries = fs::read_dir(subpath).map_err(|_| ())?;
+    /// names of the images directly inside of a folder
+    fn read_images_names(subpath: &str) -> Result<Vec<String>, AppError> {
+        let mut entries = fs::read_dir(subpath).map_err(|err| AppError::io(subpath, err))?;
         let mut imgs_name = vec![];
         while let Some(Ok(entry)) = entries.next() {
-            let name = tsuts!(entry.file_name());
-            let without_ext = match name.split('.').next() {
-                Some(n) => n,
-                None => continue,
-            };
-            // fastest/cheapest way to find the first image, if not accurate, get all images and sort to find first one...
-            if [".jpg", ".png", ".jpeg", ".webp", ".gif"]
-                .iter()
-                .any(|ext| name.ends_with(ext))
-                && ["1", "01", "01_1", "001", "001_1", "0001", "0001_1", "00001"]
-                    .iter()
-                    .any(|x| &without_ext == x)
-            {
-                return Ok(name);
+            let name = entry.file_name().to_string_lossy().to_string();
+            if MediaType::from_filename(&name) == Some(MediaType::Image) {
+                imgs_name.push(name);
             }
-            imgs_name.push(name);
         }
+        Ok(imgs_name)
+    }
 
-        imgs_name.sort();
-        match imgs_name.first() {
-            Some(n) => Ok(n.to_owned()),
-            None => Err(()),
+    /// return the thumbnail (the cover) of the subfolder along with its number of images
+    fn get_thumbnail(subpath: &str) -> Result<(String, usize), AppError> {
+        let imgs_name = Self::read_images_names(subpath)?;
+
+        let names = imgs_name.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+        match cover::find_cover(&names, cover::cover_patterns()) {
+            Some(n) => Ok((n.to_owned(), imgs_name.len())),
+            None => Err(AppError::NoThumbnail {
+                path: subpath.to_string(),
+            }),
         }
     }
 
     pub fn get_path(&self) -> String {
         format!("{}/{}", self.root_path, self.name)
     }
+
+    /// names of all the images of the subfolder, in natural order
+    pub fn get_images(&self) -> Result<Vec<String>, AppError> {
+        let mut imgs_name = Self::read_images_names(&self.get_path())?;
+        imgs_name.sort_by(|a, b| cover::natural_cmp(a, b));
+        Ok(imgs_name)
+    }
 }
 
 impl Openable for SubFolder {
@@ -90,33 +136,75 @@ impl Openable for SubFolder {
     }
 }
 
+impl DeckItem for SubFolder {
+    fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn root(&self) -> &str {
+        &self.root_path
+    }
+
+    fn count(&self) -> Option<usize> {
+        Some(self.images_count)
+    }
+
+    fn path(&self) -> String {
+        self.get_path()
+    }
+
+    fn mtime(&self) -> Option<u64> {
+        self.mtime
+    }
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum MediaType {
     Image,
     Video,
 }
 
+impl MediaType {
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let extension = tsuts!(Path::new(filename).extension()?).to_lowercase();
+        match extension.as_str() {
+            "jpg" | "jpeg" | "png" | "gif" | "jpe" | "webp" | "tiff" | "ico" | "heif" | "heic"
+            | "tif" | "jif" | "jfif" | "svg" => Some(MediaType::Image),
+            "mp4" | "webm" | "avi" | "mov" | "mkv" | "mpeg" | "m4v" | "wmv" | "flv" => {
+                Some(MediaType::Video)
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Media {
     root_path: String,
+    key: String,
 
     pub name: String,
     pub filepath: String,
     pub media_type: MediaType,
+    pub mtime: Option<u64>,
 }
 
 impl Media {
+    /// `name` is the path of the media relative to the root folder
     pub fn new(name: &str, root_path: &str, media_type: MediaType) -> Self {
+        let filename = name.rsplit('/').next().unwrap_or(name);
         Self {
             root_path: root_path.to_string(),
+            key: name.to_string(),
 
-            name: name
+            name: filename
                 .split(".")
                 .next()
                 .map(|n| n.to_string())
-                .unwrap_or(name.to_string()),
+                .unwrap_or(filename.to_string()),
             filepath: format!("{}/{}", root_path, name),
             media_type,
+            mtime: None,
         }
     }
 }
@@ -131,105 +219,651 @@ impl Openable for Media {
     }
 }
 
+impl DeckItem for Media {
+    fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn root(&self) -> &str {
+        &self.root_path
+    }
+
+    fn path(&self) -> String {
+        self.filepath.clone()
+    }
+
+    fn mtime(&self) -> Option<u64> {
+        self.mtime
+    }
+}
+
+/// item of the mixed deck, where the subfolders, images and videos are shuffled together
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum AnyItem {
+    SubFolder(SubFolder),
+    Media(Media),
+}
+
+impl AnyItem {
+    /// mode of the kind of the item, its rating, tags and note are shared with this mode
+    pub fn app_mode(&self) -> AppMode {
+        match self {
+            AnyItem::SubFolder(_) => AppMode::SubFolders,
+            AnyItem::Media(m) if m.media_type == MediaType::Image => AppMode::Images,
+            AnyItem::Media(_) => AppMode::Videos,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            AnyItem::SubFolder(sf) => &sf.name,
+            AnyItem::Media(m) => &m.name,
+        }
+    }
+}
+
+impl Openable for AnyItem {
+    fn open_dir(&self) -> bool {
+        match self {
+            AnyItem::SubFolder(sf) => sf.open_dir(),
+            AnyItem::Media(m) => m.open_dir(),
+        }
+    }
+
+    fn open_image(&self) -> bool {
+        match self {
+            AnyItem::SubFolder(sf) => sf.open_image(),
+            AnyItem::Media(m) => m.open_image(),
+        }
+    }
+}
+
+impl DeckItem for AnyItem {
+    fn key(&self) -> &str {
+        match self {
+            AnyItem::SubFolder(sf) => sf.key(),
+            AnyItem::Media(m) => m.key(),
+        }
+    }
+
+    fn root(&self) -> &str {
+        match self {
+            AnyItem::SubFolder(sf) => sf.root(),
+            AnyItem::Media(m) => m.root(),
+        }
+    }
+
+    fn count(&self) -> Option<usize> {
+        match self {
+            AnyItem::SubFolder(sf) => sf.count(),
+            AnyItem::Media(m) => m.count(),
+        }
+    }
+
+    fn path(&self) -> String {
+        match self {
+            AnyItem::SubFolder(sf) => sf.path(),
+            AnyItem::Media(m) => m.path(),
+        }
+    }
+
+    fn mtime(&self) -> Option<u64> {
+        match self {
+            AnyItem::SubFolder(sf) => sf.mtime(),
+            AnyItem::Media(m) => m.mtime(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct AppFolderManager {
+    /// identifies the session (its deck and library), the root itself or the roots of a multi-root session
+    /// separated by new lines
     pub root_path: String,
+    pub roots: Vec<String>,
 
     pub subfolders: Vec<SubFolder>,
     pub images: Vec<Media>,
     pub videos: Vec<Media>,
+    /// the items of the three kinds, shuffled together
+    pub all: Vec<AnyItem>,
 
     pub curr: usize,
+    seen: SeenDeck,
+    library: Library,
+    filter: Filter,
+    /// items that don't match the filter, kept aside until it changes
+    filtered_out: ScannedItems,
+    /// explain the last weighted pick
+    pub last_pick: Option<PickDetails>,
+    /// the items are still being scanned
+    scanning: bool,
+    /// mode of the item picked among the items scanned so far, it's picked again from the whole deck once
+    /// the scan is done (unless the user moved on)
+    provisional_pick: Option<AppMode>,
 }
 
 impl AppFolderManager {
-    /// scans and construct the folder datas, additionaly it give insight to the app on the right app mode to use depending
-    /// on the folder content
-    pub fn set_folder(root_path: String) -> Result<(Self, Option<AppMode>), ()> {
-        let (sf, img, vid, rec_app_mode) = Self::scan_folder(&root_path).map_err(|_| ())?;
+    /// empty session of one or several roots, its items are added as they are scanned (see
+    /// `scanner::scan_roots`)
+    pub fn new(roots: Vec<String>) -> Self {
+        let mut roots = roots.iter().map(|r| normalize_path(r)).collect::<Vec<_>>();
+        roots.sort();
+        roots.dedup();
+        let root_path = roots.join("\n");
+        let seen = SeenDeck::load(&root_path);
+        Self {
+            curr: 0,
 
-        Ok((
-            Self {
-                curr: 0,
+            subfolders: vec![],
+            images: vec![],
+            videos: vec![],
+            all: vec![],
 
-                subfolders: sf,
-                images: img,
-                videos: vid,
+            filter: Filter::parse(&seen.filter).unwrap_or_default(),
+            filtered_out: ScannedItems::default(),
+            seen,
+            library: Library::load(&root_path),
+            root_path,
+            roots,
+            last_pick: None,
+            scanning: true,
+            provisional_pick: None,
+        }
+    }
 
-                root_path,
-            },
-            rec_app_mode,
-        ))
+    /// merge newly scanned items into the decks, they are shuffled among the remaining items not yet seen,
+    /// the ones that don't match the filter are kept aside
+    pub fn add_items(&mut self, items: ScannedItems, app_mode: AppMode) {
+        self.add_to_decks(items, app_mode, false);
     }
 
-    fn scan_folder(
-        root_folder: &str,
-    ) -> Result<(Vec<SubFolder>, Vec<Media>, Vec<Media>, Option<AppMode>), ()> {
-        let mut sf = Vec::new();
-        let mut img = Vec::new();
-        let mut vid = Vec::new();
+    /// `keep_order` inserts the items without changing the order of the items already in the decks
+    fn add_to_decks(&mut self, items: ScannedItems, app_mode: AppMode, keep_order: bool) {
+        let items = self.session_keys(items);
+        let (filter, library) = (&self.filter, &self.library);
+        let (sf, out_sf) = split_filtered(items.sf, filter, library, &AppMode::SubFolders);
+        let (img, out_img) = split_filtered(items.img, filter, library, &AppMode::Images);
+        let (vid, out_vid) = split_filtered(items.vid, filter, library, &AppMode::Videos);
+        let all = sf
+            .iter()
+            .map(|sf| AnyItem::SubFolder(sf.clone()))
+            .chain(img.iter().chain(&vid).map(|m| AnyItem::Media(m.clone())))
+            .collect::<Vec<_>>();
+        self.filtered_out.extend(ScannedItems {
+            sf: out_sf,
+            img: out_img,
+            vid: out_vid,
+        });
 
-        let mut entries = fs::read_dir(root_folder).map_err(|_| ())?;
-        while let Some(Ok(entry)) = entries.next() {
-            let filename = tsuts!(entry.file_name());
-            match entry.file_type().map(|ft| ft.is_dir()) {
-                Ok(true) => sf.push(SubFolder::new(&tsuts!(entry.file_name()), root_folder)),
-                Ok(false) => {
-                    let extension = tsuts!(Path::new(&filename).extension().ok_or(())?);
-                    match extension.as_str() {
-                        "jpg" | "jpeg" | "png" | "gif" | "jpe" | "webp" | "tiff" | "ico"
-                        | "heif" | "heic" | "tif" | "jif" | "jfif" | "svg" => {
-                            img.push(Media::new(&filename, root_folder, MediaType::Image))
-                        }
-                        "mp4" | "webm" | "avi" | "mov" | "mkv" | "mpeg" | "m4v" | "wmv" | "flv" => {
-                            vid.push(Media::new(&filename, root_folder, MediaType::Video))
-                        }
-                        _ => continue,
-                    }
-                }
-                Err(_) => continue,
+        let curr = self.curr;
+        let start = |deck_mode: AppMode, len: usize| match deck_mode == app_mode && len > 0 {
+            true => curr + 1, // keep what was already shown of the current deck
+            false => 0,
+        };
+        let (sf_start, img_start, vid_start, all_start) = (
+            start(AppMode::SubFolders, self.subfolders.len()),
+            start(AppMode::Images, self.images.len()),
+            start(AppMode::Videos, self.videos.len()),
+            start(AppMode::All, self.all.len()),
+        );
+        let seen = &self.seen;
+        add_to_deck(
+            &mut self.subfolders,
+            sf_start,
+            sf,
+            seen,
+            &AppMode::SubFolders,
+            keep_order,
+        );
+        add_to_deck(
+            &mut self.images,
+            img_start,
+            img,
+            seen,
+            &AppMode::Images,
+            keep_order,
+        );
+        add_to_deck(
+            &mut self.videos,
+            vid_start,
+            vid,
+            seen,
+            &AppMode::Videos,
+            keep_order,
+        );
+        add_to_deck(
+            &mut self.all,
+            all_start,
+            all,
+            seen,
+            &AppMode::All,
+            keep_order,
+        );
+    }
+
+    /// once the scan is complete, forget the seen items that no longer exist in the root. The item shown while
+    /// scanning only came from the items found first, it's picked again from the whole deck so that the first
+    /// pick doesn't depend on the order the items were found in
+    pub fn finish_scan(&mut self) {
+        self.scanning = false;
+        if let Some(app_mode) = self.provisional_pick.take() {
+            if let Some(key) = self.curr_key(app_mode.clone()).map(|k| k.to_string()) {
+                self.seen.unmark(&app_mode, &key);
             }
+            self.reset_curr_index(app_mode);
         }
+        let out = &self.filtered_out;
+        self.seen
+            .retain(&AppMode::SubFolders, &keys_with(&self.subfolders, &out.sf));
+        self.seen
+            .retain(&AppMode::Images, &keys_with(&self.images, &out.img));
+        self.seen
+            .retain(&AppMode::Videos, &keys_with(&self.videos, &out.vid));
+        // the mixed deck holds the items of the three kinds
+        let mut all_keys = keys_with(&self.subfolders, &out.sf);
+        all_keys.extend(keys_with(&self.images, &out.img));
+        all_keys.extend(keys_with(&self.videos, &out.vid));
+        self.seen.retain(&AppMode::All, &all_keys);
+        let _ = self.seen.save();
+    }
 
-        let mut rng = WyRand::new();
-        rng.shuffle(&mut sf);
-        rng.shuffle(&mut img);
-        rng.shuffle(&mut vid);
+    /// add the items that appeared in the root since it was scanned, return how many were actually new. They
+    /// are slipped among the remaining items, the upcoming picks stay the same
+    pub fn insert_new_items(&mut self, items: ScannedItems, app_mode: AppMode) -> usize {
+        let mut items = self.session_keys(items);
+        let out = &self.filtered_out;
+        let known = keys_with(&self.subfolders, &out.sf);
+        items.sf.retain(|i| !known.contains(i.key()));
+        let known = keys_with(&self.images, &out.img);
+        items.img.retain(|i| !known.contains(i.key()));
+        let known = keys_with(&self.videos, &out.vid);
+        items.vid.retain(|i| !known.contains(i.key()));
 
-        let recommanded_app_mode = match (sf.is_empty(), img.is_empty(), vid.is_empty()) {
-            (true, false, _) => Some(AppMode::Images),
-            (true, true, false) => Some(AppMode::Videos),
-            _ => None,
+        let count = items.len();
+        self.add_to_decks(items, app_mode, true);
+        count
+    }
+
+    /// whether the items come from several roots
+    pub fn is_multi_root(&self) -> bool {
+        self.roots.len() > 1
+    }
+
+    /// in a multi-root session the same relative path can exist in several roots, the items are keyed by
+    /// their absolute path instead
+    fn session_keys(&self, mut items: ScannedItems) -> ScannedItems {
+        if self.is_multi_root() {
+            items.sf.iter_mut().for_each(|sf| sf.key = sf.get_path());
+            items
+                .img
+                .iter_mut()
+                .for_each(|i| i.key = i.filepath.clone());
+            items
+                .vid
+                .iter_mut()
+                .for_each(|v| v.key = v.filepath.clone());
+        }
+        items
+    }
+
+    /// remove the items at `rel_path` of `root` (or inside of it, if it's a directory), `curr` keeps pointing to
+    /// the same item, or to the next one if the current item itself was removed. Return the number of removed items
+    pub fn remove_items(&mut self, root: &str, rel_path: &str, app_mode: AppMode) -> usize {
+        let rel_path = match self.is_multi_root() {
+            true => &format!("{root}/{rel_path}"),
+            false => rel_path,
         };
+        let curr_key = self.curr_key(app_mode.clone()).map(|k| k.to_string());
+        let curr = self.curr;
+        let (sf_removed, sf_curr) = remove_from_deck(&mut self.subfolders, curr, rel_path);
+        let (img_removed, img_curr) = remove_from_deck(&mut self.images, curr, rel_path);
+        let (vid_removed, vid_curr) = remove_from_deck(&mut self.videos, curr, rel_path);
+        let (_, all_curr) = remove_from_deck(&mut self.all, curr, rel_path);
+        self.curr = match app_mode {
+            AppMode::SubFolders => sf_curr,
+            AppMode::Images => img_curr,
+            AppMode::Videos => vid_curr,
+            AppMode::All => all_curr,
+        };
+
+        if self.curr_key(app_mode.clone()).map(|k| k.to_string()) != curr_key {
+            self.mark_curr_seen(app_mode);
+        }
 
-        Ok((sf, img, vid, recommanded_app_mode))
+        let out = &mut self.filtered_out;
+        let (sf_out_removed, _) = remove_from_deck(&mut out.sf, 0, rel_path);
+        let (img_out_removed, _) = remove_from_deck(&mut out.img, 0, rel_path);
+        let (vid_out_removed, _) = remove_from_deck(&mut out.vid, 0, rel_path);
+        sf_removed + img_removed + vid_removed + sf_out_removed + img_out_removed + vid_out_removed
     }
 
-    pub fn reset_curr_index(&mut self) {
-        self.curr = 0
+    pub fn filter(&self) -> &str {
+        &self.seen.filter
     }
 
-    pub fn next(&mut self, app_mode: AppMode) -> Result<(), ()> {
-        let next_curr = self.curr.checked_add(1).ok_or(())?;
+    /// change the filter (remembered per root) and apply it to the decks, the items that match it again are
+    /// shuffled among the remaining ones and the current item stays if it still matches
+    pub fn set_filter(&mut self, expr: &str, app_mode: AppMode) -> Result<(), AppError> {
+        self.filter = Filter::parse(expr).map_err(|reason| AppError::InvalidFilter {
+            filter: expr.to_string(),
+            reason,
+        })?;
+        self.seen.filter = expr.trim().to_string();
+        let _ = self.seen.save();
+
+        let curr_key = self.curr_key(app_mode.clone()).map(|k| k.to_string());
+        let curr = self.curr;
+        let (filter, library) = (&self.filter, &self.library);
+        let (sf_curr, sf_out) = filter_deck(&mut self.subfolders, curr, |i| {
+            filter.matches(i, library.get(&AppMode::SubFolders, i.key()))
+        });
+        let (img_curr, img_out) = filter_deck(&mut self.images, curr, |i| {
+            filter.matches(i, library.get(&AppMode::Images, i.key()))
+        });
+        let (vid_curr, vid_out) = filter_deck(&mut self.videos, curr, |i| {
+            filter.matches(i, library.get(&AppMode::Videos, i.key()))
+        });
+        // the items removed from the mixed deck are also in the ones of their kind
+        let (all_curr, _) = filter_deck(&mut self.all, curr, |i| {
+            filter.matches(i, library.get(&i.app_mode(), i.key()))
+        });
+        self.curr = match app_mode {
+            AppMode::SubFolders => sf_curr,
+            AppMode::Images => img_curr,
+            AppMode::Videos => vid_curr,
+            AppMode::All => all_curr,
+        };
+
+        // the items kept aside go through the new filter
+        let filtered_out = ScannedItems {
+            sf: sf_out,
+            img: img_out,
+            vid: vid_out,
+        };
+        let previously_out = std::mem::replace(&mut self.filtered_out, filtered_out);
+        self.add_items(previously_out, app_mode.clone());
+
+        if self.curr_key(app_mode.clone()).map(|k| k.to_string()) != curr_key {
+            self.last_pick = None;
+            self.mark_curr_seen(app_mode);
+        }
+        Ok(())
+    }
+
+    /// items of this mode whose name fuzzy matches the query, the best matches first
+    pub fn search(&self, app_mode: AppMode, query: &str, limit: usize) -> Vec<SearchHit> {
+        fn subfolder_hit(sf: &SubFolder) -> (&String, &str, Option<String>) {
+            let thumbnail = sf
+                .thumbnail
+                .as_ref()
+                .map(|t| format!("{}/{t}", sf.get_path()));
+            (&sf.name, sf.key(), thumbnail)
+        }
+        fn image_hit(i: &Media) -> (&String, &str, Option<String>) {
+            (&i.name, i.key(), Some(i.filepath.clone()))
+        }
+        // only the thumbnails already extracted, the search must stay responsive
+        fn video_hit(v: &Media) -> (&String, &str, Option<String>) {
+            let thumbnail = thumb_cache::get_thumbnail_path(&v.filepath)
+                .ok()
+                .filter(|t| t.exists())
+                .map(|t| tsuts!(t));
+            (&v.name, v.key(), thumbnail)
+        }
+        let hits = match app_mode {
+            AppMode::SubFolders => self
+                .subfolders
+                .iter()
+                .map(subfolder_hit)
+                .collect::<Vec<_>>(),
+            AppMode::Images => self.images.iter().map(image_hit).collect(),
+            AppMode::Videos => self.videos.iter().map(video_hit).collect(),
+            AppMode::All => self
+                .all
+                .iter()
+                .map(|item| match item {
+                    AnyItem::SubFolder(sf) => subfolder_hit(sf),
+                    AnyItem::Media(m) if m.media_type == MediaType::Image => image_hit(m),
+                    AnyItem::Media(m) => video_hit(m),
+                })
+                .collect(),
+        };
+
+        let mut scored = hits
+            .into_iter()
+            .filter_map(|(name, key, thumbnail)| {
+                let score = search::fuzzy_score(query, name)?;
+                Some((score, name, key, thumbnail))
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by_key(|hit| std::cmp::Reverse(hit.0));
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, name, key, thumbnail)| SearchHit {
+                key: key.to_string(),
+                name: name.to_string(),
+                thumbnail,
+            })
+            .collect()
+    }
+
+    /// items of this mode in the deck order, with the file their thumbnail is made from
+    pub fn gallery(&self, app_mode: AppMode) -> Vec<GalleryEntry> {
+        let subfolder_entry = |sf: &SubFolder| GalleryEntry {
+            key: sf.key().to_string(),
+            name: sf.name.clone(),
+            source: sf
+                .thumbnail
+                .as_ref()
+                .map(|t| format!("{}/{t}", sf.get_path())),
+            video: false,
+        };
+        let media_entry = |m: &Media| GalleryEntry {
+            key: m.key().to_string(),
+            name: m.name.clone(),
+            source: Some(m.filepath.clone()),
+            video: m.media_type == MediaType::Video,
+        };
         match app_mode {
-            AppMode::SubFolders => {
-                if next_curr >= self.subfolders.len() {
-                    return Err(());
+            AppMode::SubFolders => self.subfolders.iter().map(subfolder_entry).collect(),
+            AppMode::Images => self.images.iter().map(media_entry).collect(),
+            AppMode::Videos => self.videos.iter().map(media_entry).collect(),
+            AppMode::All => self
+                .all
+                .iter()
+                .map(|item| match item {
+                    AnyItem::SubFolder(sf) => subfolder_entry(sf),
+                    AnyItem::Media(m) => media_entry(m),
+                })
+                .collect(),
+        }
+    }
+
+    /// make an item the current one, it's moved right after the current item so that the rest of the deck
+    /// keeps its order, return whether it was found
+    pub fn jump_to(&mut self, app_mode: AppMode, key: &str) -> bool {
+        let curr = self.curr;
+        let index = match app_mode {
+            AppMode::SubFolders => self.subfolders.iter().position(|i| i.key() == key),
+            AppMode::Images => self.images.iter().position(|i| i.key() == key),
+            AppMode::Videos => self.videos.iter().position(|i| i.key() == key),
+            AppMode::All => self.all.iter().position(|i| i.key() == key),
+        };
+        self.provisional_pick = None;
+        let index = match index {
+            Some(i) if i == curr => return true,
+            Some(i) => i,
+            None => return false,
+        };
+        self.curr = match app_mode {
+            AppMode::SubFolders => move_after(&mut self.subfolders, index, curr),
+            AppMode::Images => move_after(&mut self.images, index, curr),
+            AppMode::Videos => move_after(&mut self.videos, index, curr),
+            AppMode::All => move_after(&mut self.all, index, curr),
+        };
+        self.last_pick = None;
+        self.mark_curr_seen(app_mode);
+        true
+    }
+
+    /// whether this mode has items, even if none of them match the filter
+    pub fn has_items(&self, app_mode: AppMode) -> bool {
+        let filtered_out = match app_mode {
+            AppMode::SubFolders => self.filtered_out.sf.len(),
+            AppMode::Images => self.filtered_out.img.len(),
+            AppMode::Videos => self.filtered_out.vid.len(),
+            AppMode::All => self.filtered_out.len(),
+        };
+        !self.is_empty(app_mode) || filtered_out > 0
+    }
+
+    /// the directories whose content make the items of `root` (relative to it, "" being the root itself), the
+    /// parents of its items and the subfolders the scan goes into
+    pub fn watched_dirs(&self, root: &str) -> HashSet<String> {
+        let prefix = format!("{root}/");
+        let rel_paths = self
+            .subfolders
+            .iter()
+            .map(|sf| sf.path())
+            .chain(self.images.iter().map(|i| i.path()))
+            .chain(self.videos.iter().map(|v| v.path()))
+            .chain(self.filtered_out.sf.iter().map(|sf| sf.path()))
+            .chain(self.filtered_out.img.iter().map(|i| i.path()))
+            .chain(self.filtered_out.vid.iter().map(|v| v.path()))
+            .filter_map(|path| path.strip_prefix(&prefix).map(|p| p.to_string()))
+            .collect::<Vec<_>>();
+
+        let mut dirs = HashSet::from([String::new()]);
+        // the subfolders whose content is scanned too, even if they don't hold any media yet
+        let max_depth = self.scan_options().max_depth;
+        let subfolders = self.subfolders.iter().chain(&self.filtered_out.sf);
+        dirs.extend(
+            subfolders
+                .filter_map(|sf| sf.path().strip_prefix(&prefix).map(|p| p.to_string()))
+                .filter(|rel_path| rel_path.split('/').count() < max_depth),
+        );
+        for rel_path in &rel_paths {
+            let mut parent = rel_path.as_str();
+            while let Some((p, _)) = parent.rsplit_once('/') {
+                if !dirs.insert(p.to_string()) {
+                    break; // its ancestors are already there
                 }
+                parent = p;
             }
-            AppMode::Images => {
-                if next_curr >= self.images.len() {
-                    return Err(());
+        }
+        dirs
+    }
+
+    pub fn is_empty(&self, app_mode: AppMode) -> bool {
+        match app_mode {
+            AppMode::SubFolders => self.subfolders.is_empty(),
+            AppMode::Images => self.images.is_empty(),
+            AppMode::Videos => self.videos.is_empty(),
+            AppMode::All => self.all.is_empty(),
+        }
+    }
+
+    /// restart the deck of this mode from its first item, the items not yet seen are moved in front of the deck
+    /// (or in weighted mode, the first item is drawn)
+    pub fn reset_curr_index(&mut self, app_mode: AppMode) {
+        self.last_pick = None;
+        self.provisional_pick = self.scanning.then(|| app_mode.clone());
+        match self.seen.pick_mode {
+            PickMode::Shuffle => match app_mode {
+                AppMode::SubFolders => unseen_first(&mut self.subfolders, &self.seen, &app_mode),
+                AppMode::Images => unseen_first(&mut self.images, &self.seen, &app_mode),
+                AppMode::Videos => unseen_first(&mut self.videos, &self.seen, &app_mode),
+                AppMode::All => unseen_first(&mut self.all, &self.seen, &app_mode),
+            },
+            PickMode::Weighted => {
+                if let Ok((_, details)) = self.weighted_draw(None, &app_mode) {
+                    self.last_pick = details;
                 }
             }
-            AppMode::Videos => {
-                if next_curr >= self.videos.len() {
-                    return Err(());
-                }
+        }
+        self.curr = 0;
+        self.mark_curr_seen(app_mode);
+    }
+
+    /// draw the next item of the deck of this mode in weighted mode, see `weighted_draw`
+    fn weighted_draw(
+        &mut self,
+        curr: Option<usize>,
+        app_mode: &AppMode,
+    ) -> Result<(usize, Option<PickDetails>), ()> {
+        let (seen, library) = (&self.seen, &self.library);
+        let rating = |mode: &AppMode, key: &str| library.get(mode, key).and_then(|m| m.rating);
+        match app_mode {
+            AppMode::SubFolders => weighted_draw(&mut self.subfolders, curr, seen, app_mode, |i| {
+                rating(&AppMode::SubFolders, i.key())
+            }),
+            AppMode::Images => weighted_draw(&mut self.images, curr, seen, app_mode, |i| {
+                rating(&AppMode::Images, i.key())
+            }),
+            AppMode::Videos => weighted_draw(&mut self.videos, curr, seen, app_mode, |i| {
+                rating(&AppMode::Videos, i.key())
+            }),
+            // the items of the mixed deck share the ratings of their kind
+            AppMode::All => weighted_draw(&mut self.all, curr, seen, app_mode, |i| {
+                rating(&i.app_mode(), i.key())
+            }),
+        }
+    }
+
+    /// move to the next item of the deck, once every item of the root has been seen the deck is reshuffled
+    /// and a new round begins (in which case `Ok(true)` is returned)
+    pub fn next(&mut self, app_mode: AppMode) -> Result<bool, ()> {
+        self.provisional_pick = None;
+        if self.seen.pick_mode == PickMode::Weighted {
+            let curr = Some(self.curr);
+            let (new_curr, details) = self.weighted_draw(curr, &app_mode)?;
+            self.curr = new_curr;
+            self.last_pick = details;
+            self.mark_curr_seen(app_mode);
+            return Ok(false);
+        }
+
+        let next_curr = self.curr.checked_add(1).ok_or(())?;
+        let exhausted = self.is_round_over(&app_mode)?;
+
+        if exhausted {
+            self.seen.reset(&app_mode);
+            self.seen.round += 1;
+            let (curr, seen) = (self.curr, &self.seen);
+            match app_mode {
+                AppMode::SubFolders => new_round(&mut self.subfolders, curr, seen),
+                AppMode::Images => new_round(&mut self.images, curr, seen),
+                AppMode::Videos => new_round(&mut self.videos, curr, seen),
+                AppMode::All => new_round(&mut self.all, curr, seen),
             }
+            self.curr = 0;
+        } else {
+            self.curr = next_curr;
+        }
+        self.mark_curr_seen(app_mode);
+        Ok(exhausted)
+    }
+
+    /// like `next` but a new round is never started, `Err` once every item of the deck has been seen (in
+    /// weighted mode there are no rounds, an item can be drawn again)
+    pub fn next_in_round(&mut self, app_mode: AppMode) -> Result<(), ()> {
+        if self.seen.pick_mode == PickMode::Shuffle && self.is_round_over(&app_mode)? {
+            return Err(());
+        }
+        self.next(app_mode).map(|_| ())
+    }
+
+    /// whether the item after the current one was already seen in this round
+    fn is_round_over(&self, app_mode: &AppMode) -> Result<bool, ()> {
+        let next_curr = self.curr.checked_add(1).ok_or(())?;
+        match app_mode {
+            AppMode::SubFolders => is_exhausted(&self.subfolders, next_curr, &self.seen, app_mode),
+            AppMode::Images => is_exhausted(&self.images, next_curr, &self.seen, app_mode),
+            AppMode::Videos => is_exhausted(&self.videos, next_curr, &self.seen, app_mode),
+            AppMode::All => is_exhausted(&self.all, next_curr, &self.seen, app_mode),
         }
-        self.curr = next_curr;
-        Ok(())
     }
 
     pub fn prev(&mut self, app_mode: AppMode) -> Result<(), ()> {
@@ -250,50 +884,711 @@ impl AppFolderManager {
                     return Err(());
                 }
             }
+            AppMode::All => {
+                if prev_curr >= self.all.len() {
+                    return Err(());
+                }
+            }
         }
         self.curr = prev_curr;
         Ok(())
     }
+
+    pub fn curr_key(&self, app_mode: AppMode) -> Option<&str> {
+        match app_mode {
+            AppMode::SubFolders => self.subfolders.get(self.curr).map(|sf| sf.key()),
+            AppMode::Images => self.images.get(self.curr).map(|i| i.key()),
+            AppMode::Videos => self.videos.get(self.curr).map(|v| v.key()),
+            AppMode::All => self.all.get(self.curr).map(|i| i.key()),
+        }
+    }
+
+    /// root folder the current item comes from
+    pub fn curr_root(&self, app_mode: AppMode) -> Option<&str> {
+        match app_mode {
+            AppMode::SubFolders => self.subfolders.get(self.curr).map(|sf| sf.root()),
+            AppMode::Images => self.images.get(self.curr).map(|i| i.root()),
+            AppMode::Videos => self.videos.get(self.curr).map(|v| v.root()),
+            AppMode::All => self.all.get(self.curr).map(|i| i.root()),
+        }
+    }
+
+    /// absolute path and name of the current item
+    pub fn curr_item(&self, app_mode: AppMode) -> Option<(String, &str)> {
+        match app_mode {
+            AppMode::SubFolders => self
+                .subfolders
+                .get(self.curr)
+                .map(|sf| (sf.path(), sf.name.as_str())),
+            AppMode::Images => self
+                .images
+                .get(self.curr)
+                .map(|i| (i.path(), i.name.as_str())),
+            AppMode::Videos => self
+                .videos
+                .get(self.curr)
+                .map(|v| (v.path(), v.name.as_str())),
+            AppMode::All => self.all.get(self.curr).map(|i| (i.path(), i.name())),
+        }
+    }
+
+    /// kind of the current item, in the mixed mode it depends on the item (`AppMode::All` if there is none)
+    pub fn curr_kind(&self, app_mode: AppMode) -> AppMode {
+        match app_mode {
+            AppMode::All => self
+                .all
+                .get(self.curr)
+                .map(|i| i.app_mode())
+                .unwrap_or(AppMode::All),
+            _ => app_mode,
+        }
+    }
+
+    fn mark_curr_seen(&mut self, app_mode: AppMode) {
+        if let Some(key) = self.curr_key(app_mode.clone()).map(|k| k.to_string()) {
+            self.seen.mark(&app_mode, key, weighting::now());
+        }
+    }
+
+    /// write the items seen since the deck was last written
+    pub fn flush_deck(&mut self) -> Result<(), AppError> {
+        self.seen.flush()
+    }
+
+    /// mode of the session saved the last time the folder was closed
+    pub fn session_mode(&self) -> Option<AppMode> {
+        self.seen.session.as_ref().map(|s| s.app_mode.clone())
+    }
+
+    /// remember the order of the deck of this mode and the position in it
+    pub fn save_session(&mut self, app_mode: AppMode) {
+        let order = match app_mode {
+            AppMode::SubFolders => deck_keys(&self.subfolders),
+            AppMode::Images => deck_keys(&self.images),
+            AppMode::Videos => deck_keys(&self.videos),
+            AppMode::All => deck_keys(&self.all),
+        };
+        self.seen.session = Some(SavedSession {
+            app_mode,
+            order,
+            curr: self.curr,
+        });
+        let _ = self.seen.save_session();
+    }
+
+    /// Put the deck back in the order of the saved session (once the scan is done) and continue from where it
+    /// stopped. The items that appeared since are merged among the remaining unseen ones, the vanished ones are
+    /// dropped. `false` if there is no session to restore in this mode
+    pub fn restore_session(&mut self, app_mode: AppMode) -> bool {
+        if !matches!(&self.seen.session, Some(s) if s.app_mode == app_mode) {
+            return false;
+        }
+        // the item shown while scanning wasn't picked by the user, it's part of the round again
+        if let (0, Some(key)) = (self.curr, self.curr_key(app_mode.clone())) {
+            let key = key.to_string();
+            self.seen.unmark(&app_mode, &key);
+        }
+        let session = match &self.seen.session {
+            Some(s) => s,
+            None => return false,
+        };
+        let seen = &self.seen;
+        let curr = match app_mode {
+            AppMode::SubFolders => restore_order(&mut self.subfolders, session, seen),
+            AppMode::Images => restore_order(&mut self.images, session, seen),
+            AppMode::Videos => restore_order(&mut self.videos, session, seen),
+            AppMode::All => restore_order(&mut self.all, session, seen),
+        };
+        match curr {
+            Some(curr) => {
+                self.curr = curr;
+                self.last_pick = None;
+                self.mark_curr_seen(app_mode);
+                true
+            }
+            // the saved session is over, start from the unseen items
+            None => {
+                self.reset_curr_index(app_mode);
+                false
+            }
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seen.seed
+    }
+
+    /// shuffle the decks with this seed (remembered per root), everything is unseen again so that the decks
+    /// follow the seeded order from their start
+    pub fn set_seed(&mut self, seed: u64) {
+        self.reseed(seed);
+        let _ = self.seen.save();
+        let _ = self.seen.save_session();
+    }
+
+    /// shuffle the decks with this seed for this run only, the deck of the root is left untouched (nothing
+    /// seen during the run is remembered either)
+    pub fn use_seed_once(&mut self, seed: u64) {
+        self.seen.read_only = true;
+        self.reseed(seed);
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.seen.seed = seed;
+        self.seen.round = 0;
+        for app_mode in [
+            AppMode::SubFolders,
+            AppMode::Images,
+            AppMode::Videos,
+            AppMode::All,
+        ] {
+            self.seen.reset(&app_mode);
+        }
+        // the saved session follows the order of the old seed
+        self.seen.session = None;
+    }
+
+    pub fn pick_mode(&self) -> PickMode {
+        self.seen.pick_mode
+    }
+
+    pub fn favor_item_count(&self) -> bool {
+        self.seen.favor_item_count
+    }
+
+    /// the pick mode is remembered per root
+    pub fn set_pick_mode(&mut self, pick_mode: PickMode) {
+        self.seen.pick_mode = pick_mode;
+        self.last_pick = None;
+        let _ = self.seen.save();
+    }
+
+    pub fn set_favor_item_count(&mut self, favor: bool) {
+        self.seen.favor_item_count = favor;
+        let _ = self.seen.save();
+    }
+
+    pub fn scan_options(&self) -> ScanOptions {
+        self.seen.scan_options
+    }
+
+    /// the scan options are remembered per root, the folder must be rescanned for them to take effect
+    pub fn set_scan_options(&mut self, scan_options: ScanOptions) {
+        self.seen.scan_options = scan_options;
+        let _ = self.seen.save();
+    }
+
+    /// rating, tags and note of the current item
+    pub fn curr_meta(&self, app_mode: AppMode) -> Option<&ItemMeta> {
+        let key = self.curr_key(app_mode.clone())?;
+        self.library.get(&self.curr_kind(app_mode), key)
+    }
+
+    /// edit the rating, tags or note of the current item and save them
+    pub fn edit_curr_meta(
+        &mut self,
+        app_mode: AppMode,
+        edit: impl FnOnce(&mut ItemMeta),
+    ) -> Result<(), AppError> {
+        let key = match self.curr_key(app_mode.clone()) {
+            Some(k) => k.to_string(),
+            None => return Ok(()),
+        };
+        self.library.edit(&self.curr_kind(app_mode), &key, edit);
+        self.library.save()
+    }
+
+    /// number of items (matching the filter) not yet seen during the current round, number of items matching
+    /// the filter and total number of items of this mode
+    pub fn unseen_count(&self, app_mode: AppMode) -> (usize, usize, usize) {
+        let (keys, filtered_out) = match app_mode {
+            AppMode::SubFolders => (keys_of(&self.subfolders), self.filtered_out.sf.len()),
+            AppMode::Images => (keys_of(&self.images), self.filtered_out.img.len()),
+            AppMode::Videos => (keys_of(&self.videos), self.filtered_out.vid.len()),
+            AppMode::All => (keys_of(&self.all), self.filtered_out.len()),
+        };
+        let unseen = keys
+            .iter()
+            .filter(|k| !self.seen.is_seen(&app_mode, k))
+            .count();
+        (unseen, keys.len(), keys.len() + filtered_out)
+    }
+}
+
+fn keys_of<T: DeckItem>(deck: &[T]) -> HashSet<&str> {
+    deck.iter().map(|i| i.key()).collect()
+}
+
+/// keys of a deck and of its items kept aside by the filter
+fn keys_with<'a, T: DeckItem>(deck: &'a [T], filtered_out: &'a [T]) -> HashSet<&'a str> {
+    deck.iter().chain(filtered_out).map(|i| i.key()).collect()
+}
+
+fn deck_keys<T: DeckItem>(deck: &[T]) -> Vec<String> {
+    deck.iter().map(|i| i.key().to_string()).collect()
 }
 
-pub fn get_video_thumbnail(filepath: &str) -> Result<String, ()> {
-    let mut cache_path = dirs::cache_dir().ok_or(())?;
+/// order the deck like the saved session and return the new index of its current item (or of the one following
+/// it if it vanished), `None` if none of its items remain after it
+fn restore_order<T: DeckItem>(
+    deck: &mut Vec<T>,
+    session: &SavedSession,
+    seen: &SeenDeck,
+) -> Option<usize> {
+    let positions = session
+        .order
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.as_str(), i))
+        .collect::<HashMap<_, _>>();
+    let (mut known, new): (Vec<T>, Vec<T>) = deck
+        .drain(..)
+        .partition(|i| positions.contains_key(i.key()));
+    known.sort_by_key(|i| positions[i.key()]);
+
+    let curr = known
+        .iter()
+        .position(|i| positions[i.key()] >= session.curr);
+    deck.extend(known);
+    match curr {
+        Some(curr) => merge_into_deck(deck, curr + 1, new, seen, &session.app_mode),
+        None => deck.extend(new),
+    }
+    curr
+}
 
-    cache_path.push("wfns_manager");
-    fs::create_dir_all(&cache_path).map_err(|_| ())?;
+/// the items after `start` which were not seen yet are put in the seeded order along with the new ones, the seen
+/// ones stay at the end
+fn merge_into_deck<T: DeckItem>(
+    deck: &mut Vec<T>,
+    start: usize,
+    new_items: Vec<T>,
+    seen: &SeenDeck,
+    app_mode: &AppMode,
+) {
+    if new_items.is_empty() {
+        return;
+    }
+    let mut remaining = deck.split_off(start.min(deck.len()));
+    remaining.extend(new_items);
+    unseen_first(&mut remaining, seen, app_mode);
+    deck.extend(remaining);
+}
 
-    let name = {
-        let filename = filepath.split("/").last().expect("Should be a path");
-        filename.split(".").next().unwrap_or(filename)
+fn add_to_deck<T: DeckItem>(
+    deck: &mut Vec<T>,
+    start: usize,
+    new_items: Vec<T>,
+    seen: &SeenDeck,
+    app_mode: &AppMode,
+    keep_order: bool,
+) {
+    match keep_order {
+        true => insert_into_deck(deck, start, new_items, seen, app_mode),
+        false => merge_into_deck(deck, start, new_items, seen, app_mode),
+    }
+}
+
+/// the new items are put at random positions (given by the seed) among the items after `start` which were not
+/// seen yet, the order of the other items doesn't change
+fn insert_into_deck<T: DeckItem>(
+    deck: &mut Vec<T>,
+    start: usize,
+    new_items: Vec<T>,
+    seen: &SeenDeck,
+    app_mode: &AppMode,
+) {
+    let start = start.min(deck.len());
+    for item in new_items {
+        if seen.is_seen(app_mode, item.key()) {
+            deck.push(item);
+            continue;
+        }
+        let unseen = deck[start..]
+            .iter()
+            .take_while(|i| !seen.is_seen(app_mode, i.key()))
+            .count();
+        let rank = seen.rank(item.key());
+        let offset =
+            u64::from_le_bytes(rank[..8].try_into().unwrap_or_default()) % (unseen as u64 + 1);
+        deck.insert(start + offset as usize, item);
+    }
+}
+
+/// remove the items whose key is `rel_path` or is inside of it, return the number of removed items
+/// and the new index of `curr`
+fn remove_from_deck<T: DeckItem>(deck: &mut Vec<T>, curr: usize, rel_path: &str) -> (usize, usize) {
+    let prefix = format!("{rel_path}/");
+    let (new_curr, removed) = filter_deck(deck, curr, |i| {
+        i.key() != rel_path && !i.key().starts_with(&prefix)
+    });
+    (removed.len(), new_curr)
+}
+
+/// only keep the items of the deck for which `keep` is true, return the new index of `curr` (pointing to the
+/// next kept item if the current one was removed) and the removed items
+fn filter_deck<T: DeckItem>(
+    deck: &mut Vec<T>,
+    curr: usize,
+    keep: impl Fn(&T) -> bool,
+) -> (usize, Vec<T>) {
+    let removed_before = deck.iter().take(curr).filter(|i| !keep(i)).count();
+    let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(deck).into_iter().partition(|i| keep(i));
+    *deck = kept;
+    let new_curr = (curr - removed_before).min(deck.len().saturating_sub(1));
+    (new_curr, removed)
+}
+
+/// split items between the ones matching the filter and the others
+fn split_filtered<T: DeckItem>(
+    items: Vec<T>,
+    filter: &Filter,
+    library: &Library,
+    app_mode: &AppMode,
+) -> (Vec<T>, Vec<T>) {
+    if filter.is_empty() {
+        return (items, vec![]);
+    }
+    items
+        .into_iter()
+        .partition(|i| filter.matches(i, library.get(app_mode, i.key())))
+}
+
+/// the unseen items then the seen ones, both in the seeded order of the round
+fn unseen_first<T: DeckItem>(deck: &mut [T], seen: &SeenDeck, app_mode: &AppMode) {
+    deck.sort_by_cached_key(|i| (seen.is_seen(app_mode, i.key()), seen.rank(i.key())));
+}
+
+/// the items after `curr` are the unseen ones followed by the already seen ones,
+/// so the deck is exhausted when the next item has already been seen
+fn is_exhausted<T: DeckItem>(
+    deck: &[T],
+    next_curr: usize,
+    seen: &SeenDeck,
+    app_mode: &AppMode,
+) -> Result<bool, ()> {
+    if deck.is_empty() {
+        return Err(());
+    }
+    Ok(deck
+        .get(next_curr)
+        .map(|i| seen.is_seen(app_mode, i.key()))
+        .unwrap_or(true))
+}
+
+/// draw an item (other than the current one) with a probability proportional to its weight, and move it right
+/// after `curr` so that the previous picks stay reachable, return the new current index
+fn weighted_draw<T: DeckItem>(
+    deck: &mut Vec<T>,
+    curr: Option<usize>,
+    seen: &SeenDeck,
+    app_mode: &AppMode,
+    rating: impl Fn(&T) -> Option<u8>,
+) -> Result<(usize, Option<PickDetails>), ()> {
+    if deck.is_empty() {
+        return Err(());
+    }
+
+    let now = weighting::now();
+    let weights = deck
+        .iter()
+        .enumerate()
+        .map(|(i, item)| match Some(i) == curr {
+            true => None,
+            false => Some(Weight::new(
+                seen.last_shown(app_mode, item.key()),
+                item.count().filter(|_| seen.favor_item_count),
+                rating(item),
+                now,
+            )),
+        })
+        .collect::<Vec<_>>();
+    let values = weights
+        .iter()
+        .map(|w| w.map(|w| w.value()).unwrap_or(0.0))
+        .collect::<Vec<_>>();
+
+    let (picked, weight) = match weighting::draw(&values).and_then(|i| weights[i].map(|w| (i, w))) {
+        Some(p) => p,
+        None => return Ok((curr.unwrap_or(0), None)), // only the current item in the deck
+    };
+    let details = PickDetails {
+        key: deck[picked].key().to_string(),
+        weight,
+        probability: values[picked] / values.iter().sum::<f64>(),
+        candidates: weights.iter().filter(|w| w.is_some()).count(),
     };
 
-    let outname = format!("{name}_thumb.jpg");
-    let out_path = tsuts!(cache_path.join(&outname));
-    // check if already cached
-    if let Ok(true) = fs::exists(cache_path.join(&outname)) {
-        println!("Cached!");
-        return Ok(out_path);
+    let new_curr = match curr {
+        Some(c) => move_after(deck, picked, c),
+        None => {
+            // no current item yet, it becomes the first one
+            let item = deck.remove(picked);
+            deck.insert(0, item);
+            0
+        }
     };
+    Ok((new_curr, Some(details)))
+}
 
-    let cmd_status = Command::new("ffmpeg")
-        .args([
-            "-i",
-            &filepath.replace(" ", r"\ "),
-            "-vf",
-            "scale=600:-1", // thumb of 600px in width, because the gtk::Image is of width 600
-            "-frames:v",
-            "1",
-            "-q:v",
-            "2",
-            &out_path,
-        ])
-        .output()
-        .map_err(|_| ())?
-        .status;
-
-    if !cmd_status.success() {
-        return Err(());
+/// move an item right after `curr` so that the previous picks stay reachable and the rest of the deck keeps
+/// its order, return its new index
+fn move_after<T>(deck: &mut Vec<T>, index: usize, curr: usize) -> usize {
+    let item = deck.remove(index);
+    let new_index = match index < curr {
+        true => curr,
+        false => curr + 1,
+    };
+    deck.insert(new_index, item);
+    new_index
+}
+
+fn new_round<T: DeckItem>(deck: &mut [T], curr: usize, seen: &SeenDeck) {
+    let last_key = deck.get(curr).map(|i| i.key().to_string());
+    deck.sort_by_cached_key(|i| seen.rank(i.key()));
+    // don't show twice in a row the same item
+    if deck.len() > 1 && deck.first().map(|i| i.key().to_string()) == last_key {
+        deck.swap(0, deck.len() - 1);
+    }
+}
+
+/// Absolute path as the app keys the folders (decks, sessions and favorites): the `.` and `..` are resolved
+/// like in the paths of the GTK files but not the symlinks, so that a folder is the same session whether it's
+/// opened from the app or from the command line
+pub fn normalize_path(path: &str) -> String {
+    let path = Path::new(path);
+    let absolute = match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => env::current_dir().unwrap_or_default().join(path),
+    };
+    let mut normalized = PathBuf::from("/");
+    for component in absolute.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(name) => normalized.push(name),
+            _ => {}
+        }
+    }
+    normalized.to_string_lossy().to_string()
+}
+
+/// normalized path of a folder that can be opened (see `normalize_path`)
+pub fn root_dir(folder: &str) -> Result<String, AppError> {
+    let path = normalize_path(folder);
+    match fs::metadata(&path) {
+        Ok(meta) if meta.is_dir() => Ok(path),
+        Ok(_) => Err(AppError::NotADirectory {
+            path: folder.to_string(),
+        }),
+        Err(err) => Err(AppError::io(folder, err)),
     }
+}
+
+/// return the image to show for an image file, its thumbnail in the shared cache if there is one of at least
+/// `min_size` pixels, otherwise the image itself (sharper than a smaller thumbnail)
+pub fn get_image_thumbnail(filepath: &str, min_size: u32) -> String {
+    shared_thumbs::lookup(filepath, min_size)
+        .filter(|(_, size)| *size >= min_size)
+        .map(|(t, _)| tsuts!(t))
+        .unwrap_or(filepath.to_string())
+}
 
-    Ok(out_path)
+/// return the path to the thumbnail of a video, generating it if it isn't cached yet (meant to be run
+/// in a background thread). A smaller thumbnail of the shared cache is used when it can't be generated
+pub fn get_video_thumbnail(filepath: &str) -> Result<String, AppError> {
+    let shared = shared_thumbs::lookup(filepath, 512);
+    if let Some((shared, _)) = shared.as_ref().filter(|(_, size)| *size >= 512) {
+        return Ok(tsuts!(shared));
+    }
+    let out_path = thumb_cache::get_thumbnail_path(filepath)?;
+    if let Some(cached) = thumb_cache::lookup(&out_path) {
+        return Ok(tsuts!(cached));
+    }
+
+    // generated aside so that an interrupted thumbnailer doesn't leave a broken thumbnail in the cache
+    let tmp_path = out_path.with_extension("tmp.png");
+    // thumb of 600px in width, because the gtk::Image is of width 600
+    let generated = thumbnailer::generate_thumbnail(filepath, &tmp_path, 600);
+    if let Err(err) = generated {
+        let _ = fs::remove_file(&tmp_path);
+        return shared.map(|(t, _)| tsuts!(t)).ok_or(err);
+    }
+    thumb_cache::insert(&tmp_path, &out_path)?;
+    // the other apps benefit from it too, but failing to share it doesn't matter to us
+    let _ = shared_thumbs::store(filepath, &out_path);
+
+    Ok(tsuts!(out_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use nanorand::{Rng, WyRand};
+
+    use super::*;
+
+    fn deck_of(count: usize) -> Vec<Media> {
+        (0..count)
+            .map(|i| Media::new(&format!("dir/img{i}.jpg"), "/root", MediaType::Image))
+            .collect()
+    }
+
+    fn seen_with_seed(seed: u64) -> SeenDeck {
+        let mut seen = SeenDeck::default();
+        seen.seed = seed;
+        seen.mark(&AppMode::Images, "dir/img3.jpg".to_string(), 0);
+        seen.mark(&AppMode::Images, "dir/img7.jpg".to_string(), 0);
+        seen
+    }
+
+    fn folder_with_seed(seed: u64) -> AppFolderManager {
+        let mut seen = seen_with_seed(seed);
+        seen.read_only = true;
+        AppFolderManager {
+            root_path: "/root".to_string(),
+            roots: vec!["/root".to_string()],
+            subfolders: vec![],
+            images: vec![],
+            videos: vec![],
+            all: vec![],
+            curr: 0,
+            seen,
+            library: Library::default(),
+            filter: Filter::default(),
+            filtered_out: ScannedItems::default(),
+            last_pick: None,
+            scanning: true,
+            provisional_pick: None,
+        }
+    }
+
+    /// scan the items in batches, the first item is picked as soon as the first batch is found (as in the app)
+    fn first_pick(batches: Vec<Vec<Media>>) -> String {
+        let mut folder = folder_with_seed(42);
+        for img in batches {
+            let was_empty = folder.is_empty(AppMode::Images);
+            folder.add_items(
+                ScannedItems {
+                    img,
+                    ..Default::default()
+                },
+                AppMode::Images,
+            );
+            if was_empty {
+                folder.reset_curr_index(AppMode::Images);
+            }
+        }
+        folder.finish_scan();
+        folder.curr_key(AppMode::Images).unwrap().to_string()
+    }
+
+    #[test]
+    fn normalizes_the_paths_without_resolving_the_symlinks() {
+        assert_eq!(normalize_path("/a/./b//c/../d/"), "/a/b/d");
+        assert_eq!(normalize_path("/a/../.."), "/");
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(normalize_path("x/.."), cwd.to_string_lossy());
+
+        let dir = env::temp_dir().join(format!("wfns_normalize_{}", std::process::id()));
+        let (target, link) = (dir.join("target"), dir.join("link"));
+        fs::create_dir_all(&target).unwrap();
+        let _ = std::os::unix::fs::symlink(&target, &link);
+        let link = link.to_string_lossy().to_string();
+        assert_eq!(root_dir(&format!("{link}/")).unwrap(), link);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn seeded_order_ignores_the_scan_order() {
+        let seen = seen_with_seed(42);
+        let mut expected = deck_of(50);
+        unseen_first(&mut expected, &seen, &AppMode::Images);
+        let expected = deck_keys(&expected);
+
+        let mut rng = WyRand::new_seed(7);
+        for _ in 0..10 {
+            let mut deck = deck_of(50);
+            rng.shuffle(&mut deck);
+            unseen_first(&mut deck, &seen, &AppMode::Images);
+            assert_eq!(deck_keys(&deck), expected);
+        }
+        // the seen items come last
+        assert!(expected[48..].contains(&"dir/img3.jpg".to_string()));
+        assert!(expected[48..].contains(&"dir/img7.jpg".to_string()));
+    }
+
+    #[test]
+    fn seeds_give_different_orders() {
+        let (mut a, mut b) = (deck_of(50), deck_of(50));
+        unseen_first(&mut a, &seen_with_seed(1), &AppMode::Images);
+        unseen_first(&mut b, &seen_with_seed(2), &AppMode::Images);
+        assert_ne!(deck_keys(&a), deck_keys(&b));
+    }
+
+    #[test]
+    fn new_items_keep_the_order_of_the_deck() {
+        let seen = seen_with_seed(42);
+        let mut deck = deck_of(20);
+        unseen_first(&mut deck, &seen, &AppMode::Images);
+        let before = deck_keys(&deck);
+
+        let new_items = (0..5)
+            .map(|i| Media::new(&format!("new/img{i}.jpg"), "/root", MediaType::Image))
+            .collect();
+        insert_into_deck(&mut deck, 5, new_items, &seen, &AppMode::Images);
+        let after = deck_keys(&deck);
+        assert_eq!(after.len(), 25);
+        // the items already shown don't move
+        assert_eq!(after[..5], before[..5]);
+        // the new items are among the unseen ones, the others keep their order
+        let old_items = after.iter().filter(|k| !k.starts_with("new/")).cloned();
+        assert_eq!(old_items.collect::<Vec<_>>(), before);
+        let mut new_positions = after
+            .iter()
+            .enumerate()
+            .filter(|(_, k)| k.starts_with("new/"));
+        let first_seen = after.iter().position(|k| seen.is_seen(&AppMode::Images, k));
+        assert!(new_positions.all(|(i, _)| i >= 5 && Some(i) < first_seen));
+    }
+
+    #[test]
+    fn first_pick_ignores_the_scan_order() {
+        let mut deck = deck_of(30);
+        let first = deck.split_off(25);
+        let in_order = first_pick(vec![first.clone(), deck.clone()]);
+
+        let mut rng = WyRand::new_seed(7);
+        rng.shuffle(&mut deck);
+        let reversed = first.into_iter().rev().collect();
+        assert_eq!(first_pick(vec![deck, reversed]), in_order);
+
+        let mut expected = deck_of(30);
+        unseen_first(&mut expected, &seen_with_seed(42), &AppMode::Images);
+        assert_eq!(in_order, expected[0].key());
+    }
+
+    #[test]
+    fn picks_stop_at_the_end_of_the_round() {
+        let mut folder = folder_with_seed(42);
+        folder.add_items(
+            ScannedItems {
+                img: deck_of(10),
+                ..Default::default()
+            },
+            AppMode::Images,
+        );
+        folder.finish_scan();
+        folder.reset_curr_index(AppMode::Images);
+        let round = folder.seen.round;
+
+        let mut picked = vec![folder.curr_key(AppMode::Images).unwrap().to_string()];
+        while folder.next_in_round(AppMode::Images).is_ok() {
+            picked.push(folder.curr_key(AppMode::Images).unwrap().to_string());
+        }
+        // img3 and img7 were already seen in this round
+        assert_eq!(picked.len(), 8);
+        assert_eq!(picked.iter().collect::<HashSet<_>>().len(), 8);
+        assert_eq!(folder.seen.round, round);
+    }
 }
@@ -0,0 +1,60 @@
+use crate::app::{backend::SubFolder, error::AppError, gallery::GalleryEntry};
+
+/// The images of a subfolder, flipped through inside of the app
+#[derive(Debug)]
+pub struct SubFolderBrowser {
+    pub name: String,
+    path: String,
+    images: Vec<String>,
+    pub curr: usize,
+}
+
+impl SubFolderBrowser {
+    /// start at the cover of the subfolder
+    pub fn new(subfolder: &SubFolder) -> Result<Self, AppError> {
+        let images = subfolder.get_images()?;
+        let curr = subfolder
+            .thumbnail
+            .as_ref()
+            .and_then(|t| images.iter().position(|i| i == t))
+            .unwrap_or(0);
+        Ok(Self {
+            name: subfolder.name.clone(),
+            path: subfolder.get_path(),
+            images,
+            curr,
+        })
+    }
+
+    pub fn count(&self) -> usize {
+        self.images.len()
+    }
+
+    /// absolute path of the shown image
+    pub fn curr_image(&self) -> Option<String> {
+        let image = self.images.get(self.curr)?;
+        Some(format!("{}/{image}", self.path))
+    }
+
+    /// move by `delta` images, stopping at both ends
+    pub fn page(&mut self, delta: isize) {
+        self.select(self.curr.saturating_add_signed(delta));
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.curr = index.min(self.images.len().saturating_sub(1));
+    }
+
+    /// the images for the filmstrip
+    pub fn entries(&self) -> Vec<GalleryEntry> {
+        self.images
+            .iter()
+            .map(|image| GalleryEntry {
+                key: image.clone(),
+                name: image.clone(),
+                source: Some(format!("{}/{image}", self.path)),
+                video: false,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    cmp::Ordering,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use crate::app::error::AppError;
+
+/// conventional cover names, used when the user didn't write their own patterns
+const DEFAULT_COVER_PATTERNS: [&str; 4] = ["cover", "folder", "poster", "front"];
+
+static COVER_PATTERNS: OnceLock<Vec<String>> = OnceLock::new();
+
+fn patterns_path() -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("wfns_manager")
+            .join("cover_patterns"),
+    )
+}
+
+/// The patterns recognizing the cover of a subfolder, by priority. They are read from
+/// `<config dir>/wfns_manager/cover_patterns` (one pattern per line, `*` matching anything, `#` starting
+/// a comment), and are matched case-insensitively against the images name without their extension
+pub fn cover_patterns() -> &'static [String] {
+    COVER_PATTERNS.get_or_init(|| {
+        match patterns_path().and_then(|path| fs::read_to_string(path).ok()) {
+            Some(datas) => datas
+                .lines()
+                .map(|l| l.trim().to_lowercase())
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .collect(),
+            None => DEFAULT_COVER_PATTERNS.map(String::from).to_vec(),
+        }
+    })
+}
+
+/// write the default patterns file when there is none, so that the user knows where to customize them
+pub fn write_default_patterns() -> Result<(), AppError> {
+    let path = patterns_path().ok_or(AppError::NoUserDirectory("config"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| AppError::io(dir.display(), err))?;
+    }
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path);
+    let mut file = match file {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => return Ok(()),
+        Err(err) => return Err(AppError::io(path.display(), err)),
+    };
+    let datas = format!(
+        "# cover names by priority, `*` matches anything\n{}\n",
+        DEFAULT_COVER_PATTERNS.join("\n")
+    );
+    file.write_all(datas.as_bytes())
+        .map_err(|err| AppError::io(path.display(), err))
+}
+
+/// choose the cover among the images name of a folder: the first image matching a cover pattern (by pattern
+/// priority), otherwise the first image in natural order (`page2.jpg` before `page10.jpg`)
+pub fn find_cover<'a>(images: &[&'a str], patterns: &[String]) -> Option<&'a str> {
+    let stems = images
+        .iter()
+        .map(|name| {
+            let stem = name.rsplit_once('.').map(|(s, _)| s).unwrap_or(name);
+            (*name, stem.to_lowercase())
+        })
+        .collect::<Vec<_>>();
+
+    for pattern in patterns {
+        let cover = stems
+            .iter()
+            .filter(|(_, stem)| matches_pattern(stem, pattern))
+            .map(|(name, _)| *name)
+            .min_by(|a, b| natural_cmp(a, b));
+        if cover.is_some() {
+            return cover;
+        }
+    }
+    images.iter().copied().min_by(|a, b| natural_cmp(a, b))
+}
+
+/// glob-like match where `*` matches any sequence of characters
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match name.strip_prefix(first) {
+        Some(r) => r,
+        None => return false,
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+    let last = match parts.split_last() {
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(i) => rest = &rest[i + part.len()..],
+                    None => return false,
+                }
+            }
+            last
+        }
+        None => return rest.is_empty(), // no wildcard
+    };
+    rest.ends_with(last)
+}
+
+/// compare two names case-insensitively, the sequences of digits being compared by their numeric value
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let na = take_number(&mut a);
+                let nb = take_number(&mut b);
+                // "2" < "10", then "1" < "01" to keep a total order
+                let ord = na
+                    .trim_start_matches('0')
+                    .len()
+                    .cmp(&nb.trim_start_matches('0').len())
+                    .then_with(|| na.trim_start_matches('0').cmp(nb.trim_start_matches('0')))
+                    .then_with(|| na.len().cmp(&nb.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(ca), Some(cb)) => {
+                let ord = ca.to_lowercase().cmp(cb.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        number.push(c);
+    }
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn compares_numbers_by_value() {
+        assert_eq!(natural_cmp("page2", "page10"), Ordering::Less);
+        assert_eq!(natural_cmp("page10.jpg", "page2.jpg"), Ordering::Greater);
+        assert_eq!(natural_cmp("Page2", "page3"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "B"), Ordering::Less);
+        assert_eq!(natural_cmp("img", "img1"), Ordering::Less);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        // same value, the shorter number first
+        assert_eq!(natural_cmp("1", "01"), Ordering::Less);
+        assert_eq!(natural_cmp("007", "7"), Ordering::Greater);
+        assert_eq!(natural_cmp("x01", "x01"), Ordering::Equal);
+        assert_eq!(
+            natural_cmp("99999999999999999999999", "100000000000000000000000"),
+            Ordering::Less
+        );
+
+        let mut names = ["10.png", "2.png", "1.png", "01.png", "b.png", "A.png"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            ["1.png", "01.png", "2.png", "10.png", "A.png", "b.png"]
+        );
+    }
+
+    #[test]
+    fn matches_the_patterns() {
+        assert!(matches_pattern("cover", "cover"));
+        assert!(!matches_pattern("cover2", "cover"));
+        assert!(!matches_pattern("my cover", "cover"));
+        assert!(matches_pattern("cover2", "cover*"));
+        assert!(matches_pattern("my cover", "*cover"));
+        assert!(matches_pattern("00-front-hd", "*front*"));
+        assert!(matches_pattern("vol1_cover", "vol*_*"));
+        assert!(!matches_pattern("vol1", "vol*_*"));
+        assert!(matches_pattern("", "*"));
+        // the middle parts can't overlap the last one
+        assert!(!matches_pattern("ab", "a*b*b"));
+    }
+
+    #[test]
+    fn finds_the_cover() {
+        let defaults = patterns(&DEFAULT_COVER_PATTERNS);
+        // case-insensitively, without the extension
+        let images = ["page1.jpg", "Cover.JPG", "folder.png"];
+        assert_eq!(find_cover(&images, &defaults), Some("Cover.JPG"));
+        // by pattern priority
+        assert_eq!(find_cover(&images[..1], &defaults), Some("page1.jpg"));
+        assert_eq!(
+            find_cover(&["front.jpg", "poster.jpg"], &defaults),
+            Some("poster.jpg")
+        );
+        // the first match in natural order
+        let images = ["cover10.jpg", "cover2.jpg", "a.jpg"];
+        assert_eq!(
+            find_cover(&images, &patterns(&["cover*"])),
+            Some("cover2.jpg")
+        );
+        // otherwise the first image
+        assert_eq!(
+            find_cover(&["page10.jpg", "page2.jpg"], &defaults),
+            Some("page2.jpg")
+        );
+        assert_eq!(find_cover(&[], &defaults), None);
+        assert_eq!(find_cover(&["noext"], &patterns(&["noext"])), Some("noext"));
+    }
+}
@@ -0,0 +1,162 @@
+use std::{cell::RefCell, rc::Rc};
+
+use relm4::{
+    gtk::{gio, glib, prelude::ApplicationExt},
+    Sender,
+};
+
+use crate::app::{backend::root_dir, preferences::AppMode, AppInput};
+
+pub const DBUS_OBJECT_PATH: &str = "/com/ilingu/wfns_manager";
+pub const DBUS_INTERFACE: &str = "com.ilingu.wfns_manager.Picker";
+
+/// Interface exported on the session bus so that other programs (global hotkeys, launchers...) can drive the
+/// app. The modes are named "subfolders", "images", "videos" and "all".
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="com.ilingu.wfns_manager.Picker">
+    <!-- open a folder and pick from it, an empty mode keeps the current one. The path must be an existing
+         folder, relative paths are resolved from the working directory of the app -->
+    <method name="OpenRoot">
+      <arg name="path" type="s" direction="in"/>
+      <arg name="mode" type="s" direction="in"/>
+    </method>
+    <!-- pick the next item -->
+    <method name="Next"/>
+    <!-- go back to the previous item -->
+    <method name="Prev"/>
+    <!-- open the current item in the default app -->
+    <method name="OpenCurrent"/>
+    <!-- reveal the current item in the file explorer -->
+    <method name="RevealCurrent"/>
+    <!-- the current item, empty strings if there is none -->
+    <method name="GetCurrent">
+      <arg name="path" type="s" direction="out"/>
+      <arg name="name" type="s" direction="out"/>
+      <arg name="mode" type="s" direction="out"/>
+    </method>
+    <!-- emitted each time the current item changes -->
+    <signal name="PickChanged">
+      <arg name="path" type="s"/>
+      <arg name="name" type="s"/>
+      <arg name="mode" type="s"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+/// the current item as seen from the bus
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CurrentPick {
+    pub path: String,
+    pub name: String,
+    pub mode: String,
+}
+
+impl CurrentPick {
+    fn to_variant(&self) -> glib::Variant {
+        (&self.path, &self.name, &self.mode).into()
+    }
+}
+
+/// the picker object exported on the app's connection to the session bus, unregistered when dropped
+pub struct DBusService {
+    connection: gio::DBusConnection,
+    registration: Option<gio::RegistrationId>,
+    current: Rc<RefCell<CurrentPick>>,
+}
+
+impl DBusService {
+    /// export the picker, `None` if the app isn't on the session bus
+    pub fn register(app: &gio::Application, input: Sender<AppInput>) -> Option<Self> {
+        let connection = app.dbus_connection()?;
+        let interface = gio::DBusNodeInfo::for_xml(INTERFACE_XML)
+            .ok()?
+            .lookup_interface(DBUS_INTERFACE)?;
+
+        let current = Rc::new(RefCell::new(CurrentPick::default()));
+        let current_call = current.clone();
+        let registration = connection
+            .register_object(
+                DBUS_OBJECT_PATH,
+                &interface,
+                move |_, _, _, _, method, params, invocation| {
+                    let app_input = match method {
+                        "OpenRoot" => {
+                            let (path, mode) = params.get::<(String, String)>().unwrap_or_default();
+                            let mode = match mode.as_str() {
+                                "" => None,
+                                m => match AppMode::from_name(m) {
+                                    Some(m) => Some(m),
+                                    None => {
+                                        return invocation.return_error(
+                                            gio::IOErrorEnum::InvalidArgument,
+                                            &format!("Unknown mode \"{m}\""),
+                                        )
+                                    }
+                                },
+                            };
+                            // the app would only fail to scan it
+                            match root_dir(&path) {
+                                Ok(path) => AppInput::OpenRoot(path, mode),
+                                Err(err) => {
+                                    return invocation.return_error(
+                                        gio::IOErrorEnum::InvalidArgument,
+                                        &err.to_string(),
+                                    )
+                                }
+                            }
+                        }
+                        "Next" => AppInput::NextItem,
+                        "Prev" => AppInput::PrevItem,
+                        "OpenCurrent" => AppInput::OpenImg,
+                        "RevealCurrent" => AppInput::OpenDir,
+                        "GetCurrent" => {
+                            let current = current_call.borrow().to_variant();
+                            return invocation.return_value(Some(&current));
+                        }
+                        _ => {
+                            return invocation.return_error(
+                                gio::IOErrorEnum::NotSupported,
+                                &format!("Unknown method \"{method}\""),
+                            )
+                        }
+                    };
+                    input.emit(app_input);
+                    invocation.return_value(None);
+                },
+                |_, _, _, _, _| ().into(),
+                |_, _, _, _, _, _| false,
+            )
+            .ok();
+
+        Some(Self {
+            connection,
+            registration,
+            current,
+        })
+    }
+
+    /// remember the current item and tell the bus when it changed
+    pub fn set_current(&self, pick: CurrentPick) {
+        if *self.current.borrow() == pick {
+            return;
+        }
+        let _ = self.connection.emit_signal(
+            None,
+            DBUS_OBJECT_PATH,
+            DBUS_INTERFACE,
+            "PickChanged",
+            Some(&pick.to_variant()),
+        );
+        *self.current.borrow_mut() = pick;
+    }
+}
+
+impl Drop for DBusService {
+    fn drop(&mut self) {
+        if let Some(registration) = self.registration.take() {
+            let _ = self.connection.unregister_object(registration);
+        }
+    }
+}
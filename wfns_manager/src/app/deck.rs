@@ -0,0 +1,292 @@
+use nanorand::{Rng, WyRand};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self},
+    io,
+    path::PathBuf,
+};
+
+use crate::app::{
+    error::AppError, preferences::AppMode, scanner::ScanOptions, weighting::PickMode,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SeenEntry {
+    /// already shown during the current round of the shuffle bag
+    pub seen: bool,
+    /// unix timestamp (in seconds) of the last time the item was shown
+    pub last_shown: u64,
+}
+
+/// where the user stopped in the deck of a mode, the next session can continue from there
+#[derive(Debug, Clone, Default)]
+pub struct SavedSession {
+    pub app_mode: AppMode,
+    /// keys of the deck, in its order
+    pub order: Vec<String>,
+    pub curr: usize,
+}
+
+/// Persisted shuffle bag of a root folder, it remembers which items were already shown (even across sessions)
+/// so that the random picks don't repeat until every item of the root has been seen
+#[derive(Debug, Default)]
+pub struct SeenDeck {
+    root_path: String,
+
+    pub pick_mode: PickMode,
+    /// in weighted mode, also favor the items containing more elements
+    pub favor_item_count: bool,
+    pub scan_options: ScanOptions,
+    /// expression restricting the items that can be picked
+    pub filter: String,
+    /// the shuffled order of the decks only depends on it (and on the round), so it can be shared to get the
+    /// same order
+    pub seed: u64,
+    /// number of rounds started with this seed
+    pub round: u64,
+    pub session: Option<SavedSession>,
+    /// nothing is written to the deck files (e.g. a one-off seed of the command line)
+    pub read_only: bool,
+    /// items were marked since the deck was written, see `flush`
+    dirty: bool,
+
+    subfolders: HashMap<String, SeenEntry>,
+    images: HashMap<String, SeenEntry>,
+    videos: HashMap<String, SeenEntry>,
+    all: HashMap<String, SeenEntry>,
+}
+
+impl SeenDeck {
+    /// return the path to the deck file of this root (stored next to the app's config file)
+    fn get_deck_file_path(root_path: &str) -> Result<PathBuf, AppError> {
+        let mut deck_path = dirs::config_dir().ok_or(AppError::NoUserDirectory("config"))?;
+
+        deck_path.push("wfns_manager");
+        deck_path.push("decks");
+        fs::create_dir_all(&deck_path).map_err(|err| AppError::io(deck_path.display(), err))?;
+
+        deck_path.push(format!("{:x}", md5::compute(root_path)));
+        Ok(deck_path)
+    }
+
+    pub fn load(root_path: &str) -> Self {
+        let mut deck = Self {
+            root_path: root_path.to_string(),
+            seed: WyRand::new().generate(),
+            ..Default::default()
+        };
+
+        // a missing or unreadable deck just means that nothing was seen yet
+        let datas = match Self::get_deck_file_path(root_path).map(fs::read_to_string) {
+            Ok(Ok(d)) => d,
+            _ => return deck,
+        };
+        // settings lines are "@<name>\t<value>", items lines are "<kind>\t<seen>\t<last shown>\t<item key>",
+        // kind being the app mode of the item (older decks only had "<kind>\t<item key>" for the seen items)
+        for line in datas.lines() {
+            let fields = line.splitn(4, '\t').collect::<Vec<_>>();
+            match fields.as_slice() {
+                ["@pick_mode", mode] => {
+                    deck.pick_mode = match *mode {
+                        "weighted" => PickMode::Weighted,
+                        _ => PickMode::Shuffle,
+                    }
+                }
+                ["@favor_item_count", favor] => deck.favor_item_count = *favor == "1",
+                ["@scan_depth", depth] => {
+                    deck.scan_options.max_depth = depth.parse().unwrap_or(1).max(1)
+                }
+                ["@leaf_folders", leaf] => deck.scan_options.leaf_folders = *leaf == "1",
+                ["@filter", filter] => deck.filter = filter.to_string(),
+                ["@seed", seed] => deck.seed = seed.parse().unwrap_or(deck.seed),
+                ["@round", round] => deck.round = round.parse().unwrap_or_default(),
+                [kind, key] => deck.insert(
+                    kind,
+                    key,
+                    SeenEntry {
+                        seen: true,
+                        last_shown: 0,
+                    },
+                ),
+                [kind, seen, last_shown, key] => deck.insert(
+                    kind,
+                    key,
+                    SeenEntry {
+                        seen: *seen == "1",
+                        last_shown: last_shown.parse().unwrap_or_default(),
+                    },
+                ),
+                _ => continue,
+            }
+        }
+        deck.session = Self::load_session(root_path);
+        deck
+    }
+
+    /// the session is in its own file, it's only written when the folder is closed
+    fn load_session(root_path: &str) -> Option<SavedSession> {
+        let session_path = Self::get_deck_file_path(root_path)
+            .ok()?
+            .with_extension("session");
+        let datas = fs::read_to_string(session_path).ok()?;
+        // "@mode\t<mode>" and "@curr\t<index>" then the keys of the deck, one per line
+        let mut session = SavedSession::default();
+        for line in datas.lines() {
+            match line.split_once('\t') {
+                Some(("@mode", mode)) => session.app_mode = AppMode::from_name(mode)?,
+                Some(("@curr", curr)) => session.curr = curr.parse().ok()?,
+                _ => session.order.push(line.to_string()),
+            }
+        }
+        Some(session).filter(|s| !s.order.is_empty())
+    }
+
+    /// write the saved session, or remove it if there is none
+    pub fn save_session(&self) -> Result<(), AppError> {
+        if self.read_only {
+            return Ok(());
+        }
+        let session_path = Self::get_deck_file_path(&self.root_path)?.with_extension("session");
+        let result = match &self.session {
+            Some(session) => {
+                let header = [
+                    format!("@mode\t{}", session.app_mode.name()),
+                    format!("@curr\t{}", session.curr),
+                ];
+                let datas = header
+                    .into_iter()
+                    .chain(session.order.iter().cloned())
+                    .collect::<Vec<_>>();
+                fs::write(&session_path, datas.join("\n"))
+            }
+            None => match fs::remove_file(&session_path) {
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                result => result,
+            },
+        };
+        result.map_err(|err| AppError::io(session_path.display(), err))
+    }
+
+    fn insert(&mut self, kind: &str, key: &str, entry: SeenEntry) {
+        let app_mode = match kind {
+            "s" => AppMode::SubFolders,
+            "i" => AppMode::Images,
+            "v" => AppMode::Videos,
+            "a" => AppMode::All,
+            _ => return,
+        };
+        self.get_mut(&app_mode).insert(key.to_string(), entry);
+    }
+
+    pub fn save(&mut self) -> Result<(), AppError> {
+        if self.read_only {
+            return Ok(());
+        }
+        self.dirty = false;
+        let deck_file_path = Self::get_deck_file_path(&self.root_path)?;
+        let settings = [
+            format!(
+                "@pick_mode\t{}",
+                match self.pick_mode {
+                    PickMode::Shuffle => "shuffle",
+                    PickMode::Weighted => "weighted",
+                }
+            ),
+            format!("@favor_item_count\t{}", self.favor_item_count as u8),
+            format!("@scan_depth\t{}", self.scan_options.max_depth),
+            format!("@leaf_folders\t{}", self.scan_options.leaf_folders as u8),
+            format!("@filter\t{}", self.filter.replace('\t', " ")),
+            format!("@seed\t{}", self.seed),
+            format!("@round\t{}", self.round),
+        ];
+        let lines = [
+            ("s", &self.subfolders),
+            ("i", &self.images),
+            ("v", &self.videos),
+            ("a", &self.all),
+        ]
+        .into_iter()
+        .flat_map(|(kind, entries)| {
+            entries
+                .iter()
+                .map(move |(key, e)| format!("{kind}\t{}\t{}\t{key}", e.seen as u8, e.last_shown))
+        });
+        let datas = settings.into_iter().chain(lines).collect::<Vec<_>>();
+        fs::write(&deck_file_path, datas.join("\n"))
+            .map_err(|err| AppError::io(deck_file_path.display(), err))
+    }
+
+    /// write the deck if items were marked since it was last written, marking an item doesn't write the whole
+    /// deck each time
+    pub fn flush(&mut self) -> Result<(), AppError> {
+        match self.dirty {
+            true => self.save(),
+            false => Ok(()),
+        }
+    }
+
+    pub fn get(&self, app_mode: &AppMode) -> &HashMap<String, SeenEntry> {
+        match app_mode {
+            AppMode::SubFolders => &self.subfolders,
+            AppMode::Images => &self.images,
+            AppMode::Videos => &self.videos,
+            AppMode::All => &self.all,
+        }
+    }
+
+    fn get_mut(&mut self, app_mode: &AppMode) -> &mut HashMap<String, SeenEntry> {
+        match app_mode {
+            AppMode::SubFolders => &mut self.subfolders,
+            AppMode::Images => &mut self.images,
+            AppMode::Videos => &mut self.videos,
+            AppMode::All => &mut self.all,
+        }
+    }
+
+    pub fn is_seen(&self, app_mode: &AppMode, key: &str) -> bool {
+        self.get(app_mode).get(key).map(|e| e.seen).unwrap_or(false)
+    }
+
+    pub fn last_shown(&self, app_mode: &AppMode, key: &str) -> Option<u64> {
+        self.get(app_mode).get(key).map(|e| e.last_shown)
+    }
+
+    pub fn mark(&mut self, app_mode: &AppMode, key: String, now: u64) {
+        self.dirty = true;
+        self.get_mut(app_mode).insert(
+            key,
+            SeenEntry {
+                seen: true,
+                last_shown: now,
+            },
+        );
+    }
+
+    /// the item wasn't really shown (e.g. it was only the one shown while the folder was scanned), it's part
+    /// of the round again (its last shown date is kept)
+    pub fn unmark(&mut self, app_mode: &AppMode, key: &str) {
+        if let Some(entry) = self.get_mut(app_mode).get_mut(key) {
+            entry.seen = false;
+            self.dirty = true;
+        }
+    }
+
+    /// position of an item in the shuffled order of the current round, it only depends on the seed, the round
+    /// and the item itself so that the order doesn't depend on the order the items are found in
+    pub fn rank(&self, key: &str) -> [u8; 16] {
+        md5::compute(format!("{}\t{}\t{key}", self.seed, self.round)).0
+    }
+
+    /// forget everything that was seen in this mode, a new round begins (the last shown dates are kept)
+    pub fn reset(&mut self, app_mode: &AppMode) {
+        self.get_mut(app_mode)
+            .values_mut()
+            .for_each(|e| e.seen = false);
+    }
+
+    /// drop the items that are no longer in the root folder
+    pub fn retain(&mut self, app_mode: &AppMode, existing: &HashSet<&str>) {
+        self.get_mut(app_mode)
+            .retain(|key, _| existing.contains(key.as_str()));
+    }
+}
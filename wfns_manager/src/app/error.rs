@@ -0,0 +1,178 @@
+use std::{fmt, io};
+
+/// Every failure of the app backend, the `Display` impl is the actionable message shown to the user while
+/// `details` gives the technical informations behind it
+#[derive(Debug)]
+pub enum AppError {
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    NotFound {
+        path: String,
+    },
+    PermissionDenied {
+        path: String,
+    },
+    NotADirectory {
+        path: String,
+    },
+    /// the user directory (config, cache...) couldn't be determined
+    NoUserDirectory(&'static str),
+    NoThumbnail {
+        path: String,
+    },
+    /// the program of a thumbnailer isn't installed
+    ThumbnailerMissing {
+        program: String,
+    },
+    ThumbnailerFailed {
+        program: String,
+        path: String,
+        stderr: String,
+    },
+    ThumbnailerTimeout {
+        program: String,
+        path: String,
+    },
+    /// no installed thumbnailer can render this type of file
+    NoThumbnailer {
+        path: String,
+        mime_type: String,
+    },
+    ConfigCorrupt {
+        path: String,
+        reason: String,
+        /// where the corrupted file was moved, `None` if it's still in the way
+        backup: Option<String>,
+    },
+    /// the media backend of gtk can't play a video
+    Playback {
+        path: String,
+        reason: String,
+    },
+    ScanCancelled,
+    /// some cached thumbnails couldn't be removed, the others were
+    CacheNotCleared {
+        errors: Vec<AppError>,
+    },
+    InvalidFilter {
+        filter: String,
+        reason: String,
+    },
+}
+
+impl AppError {
+    /// map an io error on `path` to the most specific variant
+    pub fn io(path: impl ToString, source: io::Error) -> Self {
+        let path = path.to_string();
+        match source.kind() {
+            io::ErrorKind::NotFound => Self::NotFound { path },
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied { path },
+            io::ErrorKind::NotADirectory => Self::NotADirectory { path },
+            _ => Self::Io { path, source },
+        }
+    }
+
+    pub fn details(&self) -> String {
+        match self {
+            Self::Io { path, source } => format!("{path}: {source}"),
+            Self::NotFound { path } => format!("{path}: no such file or directory"),
+            Self::PermissionDenied { path } => format!("{path}: permission denied"),
+            Self::NotADirectory { path } => format!("{path}: not a directory"),
+            Self::NoUserDirectory(kind) => {
+                format!("the user {kind} directory could not be determined from the environment")
+            }
+            Self::NoThumbnail { path } => format!("{path}: no image file in this folder"),
+            Self::ThumbnailerMissing { program } => {
+                format!("`{program}` was not found in the PATH")
+            }
+            Self::ThumbnailerFailed {
+                program,
+                path,
+                stderr,
+            } => format!("{program}: {path}\n\n{stderr}"),
+            Self::ThumbnailerTimeout { program, path } => {
+                format!("{program}: {path}: killed after taking too long")
+            }
+            Self::NoThumbnailer { path, mime_type } => {
+                format!("{path}: no thumbnailer supports the \"{mime_type}\" type")
+            }
+            Self::ConfigCorrupt {
+                path,
+                reason,
+                backup: Some(backup),
+            } => format!("{path}: {reason}, it was moved to \"{backup}\""),
+            Self::ConfigCorrupt { path, reason, .. } => format!("{path}: {reason}"),
+            Self::Playback { path, reason } => format!("{path}: {reason}"),
+            Self::ScanCancelled => "the scan was cancelled by the user".to_string(),
+            Self::CacheNotCleared { errors } => errors
+                .iter()
+                .map(|err| err.details())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::InvalidFilter { filter, reason } => format!("\"{filter}\": {reason}"),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, .. } => write!(f, "Failed to access \"{path}\""),
+            Self::NotFound { path } => write!(
+                f,
+                "\"{path}\" doesn't exist anymore, it may have been moved or its drive unmounted"
+            ),
+            Self::PermissionDenied { path } => write!(
+                f,
+                "Permission denied on \"{path}\", check that you can read this folder"
+            ),
+            Self::NotADirectory { path } => {
+                write!(f, "\"{path}\" is not a folder, please choose a folder")
+            }
+            Self::NoUserDirectory(kind) => write!(
+                f,
+                "Couldn't find your {kind} directory, check that $HOME is set"
+            ),
+            Self::NoThumbnail { path } => write!(f, "No image found in \"{path}\""),
+            Self::ThumbnailerMissing { program } => write!(
+                f,
+                "{program} is not installed, install it to see the videos thumbnails"
+            ),
+            Self::ThumbnailerFailed { program, path, .. } => {
+                write!(f, "{program} couldn't extract a thumbnail from \"{path}\"")
+            }
+            Self::ThumbnailerTimeout { path, .. } => {
+                write!(f, "Extracting the thumbnail of \"{path}\" took too long")
+            }
+            Self::NoThumbnailer { path, .. } => write!(
+                f,
+                "Nothing can render \"{path}\", install ffmpeg or a video thumbnailer to see the videos thumbnails"
+            ),
+            Self::ConfigCorrupt { path, .. } => {
+                write!(f, "The config file \"{path}\" is corrupted, the default settings are used")
+            }
+            Self::Playback { path, .. } => write!(
+                f,
+                "Can't play \"{path}\" in the app, install the GStreamer plugins for its format"
+            ),
+            Self::ScanCancelled => write!(f, "The scan was cancelled"),
+            Self::CacheNotCleared { errors } => write!(
+                f,
+                "{} thumbnails couldn't be removed from the cache, check the permissions of its folder",
+                errors.len()
+            ),
+            Self::InvalidFilter { reason, .. } => write!(f, "Invalid filter: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use crate::app::{backend::DeckItem, library::ItemMeta, weighting};
+
+const DAY: u64 = 24 * 3600;
+
+/// syntax reminder shown in the filter bar
+pub const FILTER_HELP: &str =
+    "Terms separated by spaces, all of them must match, \"-\" negates a term:\n\
+    word or name:word\tthe path contains the word\n\
+    tag:todo\t\ttagged \"todo\"\n\
+    note:word\t\tthe note contains the word\n\
+    rating>=3\t\trated 3 stars or more (=, <, <=, >, >= work for every number)\n\
+    images>=20\t\tsubfolders with at least 20 images\n\
+    modified>=2026\tmodified since 2026 (also 2026-03 or 2026-03-01)\n\
+    age<30d\t\tmodified less than 30 days ago (d, w, m or y)";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cmp {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Cmp {
+    fn compare<T: PartialOrd>(&self, value: T, target: T) -> bool {
+        match self {
+            Cmp::Lt => value < target,
+            Cmp::Le => value <= target,
+            Cmp::Eq => value == target,
+            Cmp::Ge => value >= target,
+            Cmp::Gt => value > target,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Name(String),
+    Tag(String),
+    Note(String),
+    Rating(Cmp, u8),
+    Images(Cmp, usize),
+    /// period (start and end unix timestamps) of a date
+    Modified(Cmp, u64, u64),
+    /// in seconds
+    Age(Cmp, u64),
+}
+
+/// Expression restricting the items that can be picked, see `FILTER_HELP` for its syntax
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Filter {
+    /// terms and whether they are negated
+    terms: Vec<(bool, Term)>,
+}
+
+impl Filter {
+    /// parse a filter expression, return the reason why it's invalid otherwise
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let terms = expr
+            .split_whitespace()
+            .map(|word| match word.strip_prefix('-') {
+                Some(term) if !term.is_empty() => Ok((true, parse_term(term)?)),
+                _ => Ok((false, parse_term(word)?)),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// whether an item (with its rating, tags and note) can be picked
+    pub fn matches<T: DeckItem>(&self, item: &T, meta: Option<&ItemMeta>) -> bool {
+        self.terms.iter().all(|(negated, term)| {
+            let matches = match term {
+                Term::Name(word) => item.key().to_lowercase().contains(word),
+                Term::Tag(tag) => meta.is_some_and(|m| m.tags.contains(tag)),
+                Term::Note(word) => meta.is_some_and(|m| m.note.to_lowercase().contains(word)),
+                Term::Rating(cmp, rating) => meta
+                    .and_then(|m| m.rating)
+                    .is_some_and(|r| cmp.compare(r, *rating)),
+                Term::Images(cmp, count) => item.count().is_some_and(|c| cmp.compare(c, *count)),
+                Term::Modified(cmp, start, end) => item.mtime().is_some_and(|t| match cmp {
+                    Cmp::Lt => t < *start,
+                    Cmp::Le => t < *end,
+                    Cmp::Eq => (*start..*end).contains(&t),
+                    Cmp::Ge => t >= *start,
+                    Cmp::Gt => t >= *end,
+                }),
+                Term::Age(cmp, age) => item
+                    .mtime()
+                    .is_some_and(|t| cmp.compare(weighting::now().saturating_sub(t), *age)),
+            };
+            matches != *negated
+        })
+    }
+}
+
+fn parse_term(term: &str) -> Result<Term, String> {
+    if let Some((key, value)) = term.split_once(':') {
+        let value = value.to_lowercase();
+        return match key {
+            "name" => Ok(Term::Name(value)),
+            "tag" => Ok(Term::Tag(value)),
+            "note" => Ok(Term::Note(value)),
+            _ => Err(format!("unknown term \"{key}:\"")),
+        };
+    }
+
+    let op_start = match term.find(['<', '>', '=']) {
+        Some(i) => i,
+        None => return Ok(Term::Name(term.to_lowercase())),
+    };
+    let (key, rest) = term.split_at(op_start);
+    let (cmp, value) = [
+        ("<=", Cmp::Le),
+        (">=", Cmp::Ge),
+        ("<", Cmp::Lt),
+        (">", Cmp::Gt),
+        ("=", Cmp::Eq),
+    ]
+    .into_iter()
+    .find_map(|(op, cmp)| rest.strip_prefix(op).map(|v| (cmp, v)))
+    .ok_or(format!("invalid comparison in \"{term}\""))?;
+
+    let invalid = || format!("invalid value \"{value}\" for \"{key}\"");
+    match key {
+        "rating" => Ok(Term::Rating(cmp, value.parse().map_err(|_| invalid())?)),
+        "images" => Ok(Term::Images(cmp, value.parse().map_err(|_| invalid())?)),
+        "modified" => {
+            let (start, end) = parse_date(value).ok_or_else(invalid)?;
+            Ok(Term::Modified(cmp, start, end))
+        }
+        "age" => Ok(Term::Age(cmp, parse_duration(value).ok_or_else(invalid)?)),
+        _ => Err(format!("unknown term \"{key}\"")),
+    }
+}
+
+/// "2026", "2026-03" or "2026-03-01" to the period it spans (unix timestamps, in UTC)
+fn parse_date(date: &str) -> Option<(u64, u64)> {
+    let parts = date
+        .split('-')
+        .map(|p| p.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (start, end) = match parts.as_slice() {
+        [y] => (days_from_civil(*y, 1, 1), days_from_civil(y + 1, 1, 1)),
+        [y, m] if (1..=12).contains(m) => {
+            let (next_y, next_m) = match m {
+                12 => (y + 1, 1),
+                m => (*y, m + 1),
+            };
+            (
+                days_from_civil(*y, *m, 1),
+                days_from_civil(next_y, next_m, 1),
+            )
+        }
+        [y, m, d] if (1..=12).contains(m) && (1..=31).contains(d) => {
+            let start = days_from_civil(*y, *m, *d);
+            (start, start + 1)
+        }
+        _ => return None,
+    };
+    let to_timestamp = |days: i64| (days.max(0) as u64) * DAY;
+    Some((to_timestamp(start), to_timestamp(end)))
+}
+
+/// "30d", "2w", "6m" or "1y" to seconds
+fn parse_duration(duration: &str) -> Option<u64> {
+    let unit = match duration.chars().last()? {
+        'd' => DAY,
+        'w' => 7 * DAY,
+        'm' => 30 * DAY,
+        'y' => 365 * DAY,
+        _ => return None,
+    };
+    let count = duration[..duration.len() - 1].parse::<u64>().ok()?;
+    Some(count * unit)
+}
+
+/// number of days since the unix epoch of a date of the gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::backend::{Media, MediaType};
+
+    #[test]
+    fn counts_the_days_since_the_epoch() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        // 2024 is a leap year, 2100 isn't
+        assert_eq!(
+            days_from_civil(2024, 3, 1) - days_from_civil(2024, 2, 1),
+            29
+        );
+        assert_eq!(
+            days_from_civil(2100, 3, 1) - days_from_civil(2100, 2, 1),
+            28
+        );
+        assert_eq!(days_from_civil(2026, 1, 1), 20454);
+    }
+
+    #[test]
+    fn parses_dates_to_periods() {
+        let day = |y, m, d| days_from_civil(y, m, d) as u64 * DAY;
+        assert_eq!(parse_date("2026"), Some((day(2026, 1, 1), day(2027, 1, 1))));
+        assert_eq!(
+            parse_date("2026-03"),
+            Some((day(2026, 3, 1), day(2026, 4, 1)))
+        );
+        assert_eq!(
+            parse_date("2026-12"),
+            Some((day(2026, 12, 1), day(2027, 1, 1)))
+        );
+        assert_eq!(
+            parse_date("2026-03-01"),
+            Some((day(2026, 3, 1), day(2026, 3, 2)))
+        );
+        // before the epoch
+        assert_eq!(parse_date("1960"), Some((0, 0)));
+
+        for invalid in [
+            "",
+            "2026-13",
+            "2026-00",
+            "2026-03-32",
+            "2026-3-1-1",
+            "march",
+        ] {
+            assert_eq!(parse_date(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("30d"), Some(30 * DAY));
+        assert_eq!(parse_duration("2w"), Some(14 * DAY));
+        assert_eq!(parse_duration("6m"), Some(180 * DAY));
+        assert_eq!(parse_duration("1y"), Some(365 * DAY));
+        for invalid in ["", "d", "30", "30h", "-1d", "1.5d"] {
+            assert_eq!(parse_duration(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn parses_terms() {
+        assert_eq!(parse_term("Beach"), Ok(Term::Name("beach".to_string())));
+        assert_eq!(parse_term("name:a=b"), Ok(Term::Name("a=b".to_string())));
+        assert_eq!(parse_term("tag:TODO"), Ok(Term::Tag("todo".to_string())));
+        assert_eq!(parse_term("note:x"), Ok(Term::Note("x".to_string())));
+        assert_eq!(parse_term("rating>=3"), Ok(Term::Rating(Cmp::Ge, 3)));
+        assert_eq!(parse_term("rating<3"), Ok(Term::Rating(Cmp::Lt, 3)));
+        assert_eq!(parse_term("images=20"), Ok(Term::Images(Cmp::Eq, 20)));
+        assert_eq!(parse_term("age>1w"), Ok(Term::Age(Cmp::Gt, 7 * DAY)));
+        let (start, end) = parse_date("2026").unwrap();
+        assert_eq!(
+            parse_term("modified<=2026"),
+            Ok(Term::Modified(Cmp::Le, start, end))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_terms() {
+        for invalid in [
+            "size:big",
+            "rating=>3",
+            "rating>=many",
+            "rating>=300",
+            "images<-1",
+            "modified>=yesterday",
+            "age<30h",
+            "width>10",
+        ] {
+            assert!(parse_term(invalid).is_err(), "{invalid}");
+        }
+        assert!(Filter::parse("tag:todo rating>=x").is_err());
+    }
+
+    #[test]
+    fn matches_the_modification_date_read_by_the_scan() {
+        let mut media = Media::new("dir/img.jpg", "/root", MediaType::Image);
+        media.mtime = Some(days_from_civil(2026, 3, 10) as u64 * DAY);
+        let matches = |expr: &str| Filter::parse(expr).unwrap().matches(&media, None);
+
+        assert!(matches(
+            "modified>=2026 modified=2026-03 -modified>2026-03-10"
+        ));
+        assert!(matches("modified<=2026-03-10 modified<2027"));
+        assert!(!matches("modified<2026-03-10"));
+        assert!(matches("img -tag:todo"));
+
+        media.mtime = None;
+        assert!(!Filter::parse("modified>=1970")
+            .unwrap()
+            .matches(&media, None));
+    }
+}
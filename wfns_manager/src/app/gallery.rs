@@ -0,0 +1,29 @@
+use crate::app::backend;
+
+/// size in pixels of the thumbnails of the gallery
+pub const GALLERY_THUMBNAIL_SIZE: i32 = 192;
+
+/// an item of the open root shown in the gallery
+#[derive(Debug, Clone)]
+pub struct GalleryEntry {
+    pub key: String,
+    pub name: String,
+    /// file the thumbnail is made from (the subfolder cover, the image or the video)
+    pub source: Option<String>,
+    pub video: bool,
+}
+
+impl GalleryEntry {
+    /// image to show for this item, the one of a video may have to be generated (meant to be run in a
+    /// background thread)
+    pub fn thumbnail_path(&self) -> Option<String> {
+        let source = self.source.as_ref()?;
+        match self.video {
+            true => backend::get_video_thumbnail(source).ok(),
+            false => Some(backend::get_image_thumbnail(
+                source,
+                GALLERY_THUMBNAIL_SIZE as u32,
+            )),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use crate::app::{error::AppError, preferences::AppMode};
+
+/// what the user wrote about an item
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ItemMeta {
+    /// from 1 to 5 stars
+    pub rating: Option<u8>,
+    pub tags: Vec<String>,
+    pub note: String,
+}
+
+impl ItemMeta {
+    fn is_empty(&self) -> bool {
+        self.rating.is_none() && self.tags.is_empty() && self.note.is_empty()
+    }
+}
+
+/// Persisted ratings, tags and notes of the items of a root folder
+#[derive(Debug, Default)]
+pub struct Library {
+    root_path: String,
+
+    subfolders: HashMap<String, ItemMeta>,
+    images: HashMap<String, ItemMeta>,
+    videos: HashMap<String, ItemMeta>,
+}
+
+impl Library {
+    /// return the path to the library file of this root (stored next to the app's config file)
+    fn get_library_file_path(root_path: &str) -> Result<PathBuf, AppError> {
+        let mut library_path = dirs::config_dir().ok_or(AppError::NoUserDirectory("config"))?;
+
+        library_path.push("wfns_manager");
+        library_path.push("library");
+        fs::create_dir_all(&library_path)
+            .map_err(|err| AppError::io(library_path.display(), err))?;
+
+        library_path.push(format!("{:x}", md5::compute(root_path)));
+        Ok(library_path)
+    }
+
+    pub fn load(root_path: &str) -> Self {
+        let mut library = Self {
+            root_path: root_path.to_string(),
+            ..Default::default()
+        };
+
+        // a missing or unreadable library just means that nothing was written yet
+        let datas = match Self::get_library_file_path(root_path).map(fs::read_to_string) {
+            Ok(Ok(d)) => d,
+            _ => return library,
+        };
+        // lines are "<kind>\t<rating>\t<comma separated tags>\t<escaped note>\t<item key>"
+        for line in datas.lines() {
+            let fields = line.splitn(5, '\t').collect::<Vec<_>>();
+            let [kind, rating, tags, note, key] = fields.as_slice() else {
+                continue;
+            };
+            let app_mode = match *kind {
+                "s" => AppMode::SubFolders,
+                "i" => AppMode::Images,
+                "v" => AppMode::Videos,
+                _ => continue,
+            };
+            let meta = ItemMeta {
+                rating: rating.parse().ok().filter(|r| (1..=5).contains(r)),
+                tags: parse_tags(tags),
+                note: unescape(note),
+            };
+            if let Some(items) = library.get_mut(&app_mode) {
+                items.insert(key.to_string(), meta);
+            }
+        }
+        library
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        let library_file_path = Self::get_library_file_path(&self.root_path)?;
+        let lines = [
+            ("s", &self.subfolders),
+            ("i", &self.images),
+            ("v", &self.videos),
+        ]
+        .into_iter()
+        .flat_map(|(kind, entries)| {
+            entries.iter().map(move |(key, m)| {
+                format!(
+                    "{kind}\t{}\t{}\t{}\t{key}",
+                    m.rating.unwrap_or(0),
+                    m.tags.join(","),
+                    escape(&m.note)
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+        fs::write(&library_file_path, lines.join("\n"))
+            .map_err(|err| AppError::io(library_file_path.display(), err))
+    }
+
+    /// the items of the mixed deck are stored under their own kind, `AppMode::All` has no map
+    fn get_map(&self, app_mode: &AppMode) -> Option<&HashMap<String, ItemMeta>> {
+        match app_mode {
+            AppMode::SubFolders => Some(&self.subfolders),
+            AppMode::Images => Some(&self.images),
+            AppMode::Videos => Some(&self.videos),
+            AppMode::All => None,
+        }
+    }
+
+    fn get_mut(&mut self, app_mode: &AppMode) -> Option<&mut HashMap<String, ItemMeta>> {
+        match app_mode {
+            AppMode::SubFolders => Some(&mut self.subfolders),
+            AppMode::Images => Some(&mut self.images),
+            AppMode::Videos => Some(&mut self.videos),
+            AppMode::All => None,
+        }
+    }
+
+    pub fn get(&self, app_mode: &AppMode, key: &str) -> Option<&ItemMeta> {
+        self.get_map(app_mode)?.get(key)
+    }
+
+    /// edit the metadata of an item, the items without any metadata aren't kept
+    pub fn edit(&mut self, app_mode: &AppMode, key: &str, edit: impl FnOnce(&mut ItemMeta)) {
+        let Some(items) = self.get_mut(app_mode) else {
+            return;
+        };
+        let meta = items.entry(key.to_string()).or_default();
+        edit(meta);
+        if meta.is_empty() {
+            items.remove(key);
+        }
+    }
+}
+
+/// tags written by the user, separated by commas
+pub fn parse_tags(tags: &str) -> Vec<String> {
+    let mut parsed = vec![];
+    for tag in tags.split(',').map(|t| t.trim().to_lowercase()) {
+        if !tag.is_empty() && !parsed.contains(&tag) {
+            parsed.push(tag);
+        }
+    }
+    parsed
+}
+
+/// keep the note on a single line of the library file
+fn escape(note: &str) -> String {
+    note.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(note: &str) -> String {
+    let mut unescaped = String::with_capacity(note.len());
+    let mut chars = note.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
@@ -1,28 +1,64 @@
 mod actions;
-mod backend;
+pub mod backend;
+mod browser;
+pub mod cover;
+mod dbus;
+mod deck;
+pub mod error;
+mod filter;
+pub mod gallery;
+mod library;
+mod player;
 pub mod preferences;
+pub mod scanner;
+pub mod search;
+mod shared_thumbs;
+mod thumb_cache;
+pub mod thumbnailer;
+mod watcher;
+mod weighting;
 
-use std::time::Duration;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use adw::prelude::*;
 use backend::AppFolderManager;
-use preferences::AppPreferences;
+use browser::SubFolderBrowser;
+use dbus::{CurrentPick, DBusService};
+use error::AppError;
+use player::VideoPlayer;
+use preferences::{AppPreferences, WindowState};
 use relm4::{
     abstractions::Toaster,
     actions::{AccelsPlus, RelmAction, RelmActionGroup},
     adw,
     factory::FactoryVecDeque,
-    gtk::{self, EventControllerMotion},
+    gtk::{self, gdk, gdk_pixbuf::Pixbuf, glib, EventControllerMotion},
+    typed_view::grid::TypedGridView,
     Component, ComponentController, ComponentParts, ComponentSender, Controller, RelmWidgetExt,
-    SimpleComponent,
 };
+use scanner::{ScanOptions, ScanProgress, ScannedItems};
+use watcher::{FolderWatcher, WatchEvent};
 
 use crate::{
-    app::{backend::Openable, preferences::AppMode},
+    app::{
+        backend::{AnyItem, MediaType, Openable},
+        preferences::AppMode,
+        weighting::PickMode,
+    },
     components::{
         about::{AboutInput, AboutPageModel},
+        cache_dialog::show_cache_dialog,
+        error_dialog::show_error_dialog,
         fav_folder::{FavFolderModel, FavFolderOutput},
+        gallery_item::GalleryItem,
         header::{HeaderInput, HeaderModel, HeaderOutput},
+        search_result::{SearchResultModel, SearchResultOutput},
         shortcuts::build_shortcuts_window,
     },
     init_app_actions,
@@ -35,10 +71,30 @@ macro_rules! push_toast {
         $sender.input(AppInput::PushToast((
             $e.to_string(),
             Duration::from_secs($f),
+            None,
         )))
     };
 }
 
+/// toast the error message, with a button to see its details
+macro_rules! push_error {
+    ($err:expr, $sender:expr) => {{
+        let err: AppError = $err;
+        $sender.input(AppInput::PushToast((
+            err.to_string(),
+            Duration::from_secs(5),
+            Some(err),
+        )))
+    }};
+}
+
+/// the search only shows the best matches
+const SEARCH_RESULTS_LIMIT: usize = 50;
+/// images skipped by page up/down in the subfolder browser
+const BROWSER_PAGE: isize = 10;
+/// the images of the subfolder browser are decoded at this size, they are shown about this big
+const BROWSER_IMAGE_SIZE: i32 = 1024;
+
 // actions
 relm4::new_action_group!(ShortcutsActionGroup, "app_shortcuts");
 relm4::new_stateless_action!(NextSFAction, ShortcutsActionGroup, "next");
@@ -46,6 +102,9 @@ relm4::new_stateless_action!(PrevSFAction, ShortcutsActionGroup, "prev");
 relm4::new_stateless_action!(OpenNewDir, ShortcutsActionGroup, "open_new_dir");
 relm4::new_stateless_action!(OpenSFImg, ShortcutsActionGroup, "open_sf_img");
 relm4::new_stateless_action!(OpenSF, ShortcutsActionGroup, "open_sf");
+relm4::new_stateless_action!(OpenSearch, ShortcutsActionGroup, "open_search");
+relm4::new_stateless_action!(ToggleGallery, ShortcutsActionGroup, "toggle_gallery");
+relm4::new_stateless_action!(ToggleBrowser, ShortcutsActionGroup, "toggle_browser");
 
 // Model
 
@@ -53,18 +112,75 @@ relm4::new_stateless_action!(OpenSF, ShortcutsActionGroup, "open_sf");
 pub enum AppPages {
     ChooseFolder,
     ViewFolder,
+    /// every item of the folder at once
+    Gallery,
+    /// the images of the current subfolder
+    Browse,
+}
+
+/// image shown for the current item
+#[derive(Debug, PartialEq)]
+pub enum Thumbnail {
+    Loading,
+    Ready(String),
+    /// nothing could render the item (a video), an icon is shown instead
+    Fallback,
 }
 
 pub struct AppModel {
     prefs: AppPreferences,
     current_page: AppPages,
     curr_folder: Option<AppFolderManager>,
+    /// file of the current item whose thumbnail is shown (the subfolder cover, the image or the video) and
+    /// this thumbnail
+    thumbnail: (Option<String>, Option<Thumbnail>),
+    /// the running scan (cancel flag and progress), `scan_id` tells apart the messages of an outdated scan
+    scan: Option<(Arc<AtomicBool>, ScanProgress)>,
+    scan_id: usize,
+    /// the folder being scanned continues its last session once the scan is done
+    restoring: bool,
+    /// one per root of the session
+    watchers: Vec<FolderWatcher>,
+    /// changes of the open roots waiting to be applied (created paths with their root and number of removed items)
+    pending_changes: Option<(Vec<(String, String)>, usize)>,
+    /// scan depth chosen for a session (its root path) and number of steps of the spin row so far, the depth is
+    /// applied once the user stops changing it
+    pending_scan_depth: Option<(String, usize)>,
+    scan_depth_steps: usize,
+    /// number of items marked as seen so far, the deck is written once the user pauses
+    deck_flush_steps: usize,
+    /// current item (mode and path) whose tags and note are in the entries, `item_changed` tells when they
+    /// must be replaced (not at each update, the user may be typing)
+    shown_item: Option<(AppMode, String)>,
+    item_changed: bool,
+    /// rating of the current item shown by the header, it's only sent to it when the item or its rating changes
+    shown_rating: Option<u8>,
+    /// the filter bar must show the filter of the folder (it was opened or the filter was applied)
+    filter_changed: bool,
+    filter_error: Option<String>,
+    /// seed typed on the welcome page, the next folder opened is shuffled with it (`Err` if it isn't a number)
+    seed: Result<Option<u64>, ()>,
+    /// the subfolder whose images are browsed, `browser_changed` tells when the preview must be replaced
+    browser: Option<SubFolderBrowser>,
+    browser_changed: bool,
+    /// the shown image of the browser, loaded in the background
+    browser_image: Option<gdk::Texture>,
+    /// the current video, played inside of the card
+    player: Option<VideoPlayer>,
+    /// the picker exported on the session bus
+    dbus: Option<DBusService>,
 
     // factories
     favs_folders: FactoryVecDeque<FavFolderModel>,
+    search_results: FactoryVecDeque<SearchResultModel>,
+    gallery: TypedGridView<GalleryItem, gtk::NoSelection>,
+    filmstrip: TypedGridView<GalleryItem, gtk::SingleSelection>,
 
     // components
     title_popover: gtk::Popover,
+    search_popover: gtk::Popover,
+    seed_entry: gtk::Entry,
+    browse_page: gtk::Box,
     header: Controller<HeaderModel>,
     about_page: Controller<AboutPageModel>,
     toaster: Toaster,
@@ -75,33 +191,118 @@ pub struct AppModel {
 pub enum AppInput {
     OpenAbout,
     OpenShortcuts,
+    OpenSearch,
+    Search(String),
+    /// jump to the best search result
+    SearchActivate,
+    JumpTo(String),
+    ToggleGallery,
+    /// a cell of the gallery was clicked
+    GalleryActivate(u32),
+    ToggleBrowser,
+    /// move by this number of images in the subfolder browser
+    BrowseBy(isize),
+    BrowseTo(usize),
+    TogglePlay,
+    ToggleMuted,
+    /// the pointer entered or left the still of the video
+    PreviewVideo(bool),
+    /// the playback of the current video started, stopped or was muted (from the app or the controls of the
+    /// video)
+    PlayerChanged,
+    /// the media backend can't play this video (path and reason)
+    PlayerFailed(String, String),
+    OpenThumbnailCache,
+    ClearThumbnailCache,
     ChooseFolder,
-    AddFolder(String),
+    /// open a session of one or several roots
+    AddFolders(Vec<String>),
+    OpenAllFavorites,
+    /// reopen the last folder where it was left
+    RestoreSession,
+    SetRestoreSession(bool),
+    /// open a favorite, in its own mode if it has one
+    OpenFavorite(String),
+    RemoveFavorite(String),
+    RenameFavorite(String, String),
+    SetFavoriteMode(String, Option<AppMode>),
+    /// move a favorite to this position
+    MoveFavorite(String, usize),
+    /// open a root from another program, in this mode if there is one
+    OpenRoot(String, Option<AppMode>),
     NextItem,
     PrevItem,
     OpenImg,
     OpenDir,
-    PushToast((String, Duration)),
+    PushToast((String, Duration, Option<AppError>)),
     SwitchPage(AppPages),
     SetBookmarked(bool),
     TitlePopup(bool),
     ChangeAppMode(AppMode),
+    SetPickMode(PickMode),
+    SetFavorItemCount(bool),
+    SetScanDepth(usize),
+    /// rescan with the chosen depth if it didn't change since this step
+    ApplyScanDepth(usize),
+    /// write the seen items if no other item was picked since this step
+    FlushDeck(usize),
+    SetLeafFolders(bool),
+    SetRating(Option<u8>),
+    SetTags(String),
+    SetNote(String),
+    SetFilter(String),
+    SetSeed(String),
+    CancelScan,
+    /// change in this root of the session
+    FolderChanged(String, WatchEvent),
+    FlushFolderChanges,
+}
+
+#[derive(Debug)]
+pub enum AppCommand {
+    ScanUpdate(usize, ScannedItems, ScanProgress),
+    ScanDone(usize, Result<Option<AppMode>, AppError>),
+    /// items that appeared in the open root, new directories to watch (root and path relative to it) and
+    /// number of removed items
+    FolderChanged(usize, ScannedItems, Vec<(String, String)>, usize),
+    /// a root of a multi-root session couldn't be scanned, the others are still shown
+    ScanError(usize, AppError),
+    /// thumbnail of the current item found in the background (generated for a video)
+    ItemThumbnail(String, Result<String, AppError>),
+    /// image of the browser decoded in the background, `None` if it can't be
+    BrowserImage(String, Option<gdk::Texture>),
 }
 
 // component
 #[relm4::component(pub)]
-impl SimpleComponent for AppModel {
+impl Component for AppModel {
     type Input = AppInput;
     type Output = ();
-    type Init = AppPreferences;
+    type CommandOutput = AppCommand;
+    type Init = (AppPreferences, Option<AppError>);
 
     view! {
         main_window = gtk::Window {
             set_title: Some("WFNS Manager"),
-            set_maximized: true,
+            set_default_size: (model.prefs.window.width, model.prefs.window.height),
+            set_maximized: model.prefs.window.maximized,
             set_titlebar: Some(model.header.widget()),
             set_icon_name: Some("logo"),
 
+            // number keys rate the current item, the entries consume them first when typing
+            add_controller = gtk::EventControllerKey {
+                connect_key_pressed[sender] => move |_, key, _, modifiers| {
+                    let rating = key.to_unicode().and_then(|c| c.to_digit(10)).filter(|r| *r <= 5);
+                    match rating {
+                        Some(r) if modifiers.is_empty() => {
+                            sender.input(AppInput::SetRating((r > 0).then_some(r as u8)));
+                            glib::Propagation::Stop
+                        }
+                        _ => glib::Propagation::Proceed,
+                    }
+                },
+            },
+
             gtk::Box {
                 set_orientation: gtk::Orientation::Vertical,
 
@@ -116,6 +317,31 @@ impl SimpleComponent for AppModel {
                         set_hexpand: true,
                         set_vexpand: true,
 
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Horizontal,
+                            set_halign: gtk::Align::Center,
+                            set_spacing: 10,
+                            #[watch]
+                            set_visible: model.scan.is_some(),
+
+                            gtk::Spinner {
+                                #[watch]
+                                set_spinning: model.scan.is_some(),
+                            },
+                            gtk::Label {
+                                #[watch]
+                                set_label: &model.scan.as_ref().map(|(_, p)| format!(
+                                    "Scanning... {} entries read, {} subfolders, {} images and {} videos found",
+                                    p.scanned, p.subfolders, p.images, p.videos
+                                )).unwrap_or_default(),
+                            },
+                            gtk::Button {
+                                set_label: "Cancel",
+                                set_css_classes: &["pill", "flat"],
+                                connect_clicked => AppInput::CancelScan,
+                            },
+                        },
+
                         // Here lie the app UI code
                         gtk::Stack {
                             set_transition_type: gtk::StackTransitionType::SlideLeftRight,
@@ -148,7 +374,37 @@ impl SimpleComponent for AppModel {
                                     set_selection_mode: gtk::SelectionMode::None,
                                     set_halign: gtk::Align::Center,
                                     set_css_classes: &["boxed-list"],
-                                    set_width_request: 275,
+                                    set_width_request: 420,
+                                    set_margin_bottom: 10,
+                                    #[watch]
+                                    set_visible: !model.prefs.last_roots.is_empty(),
+
+                                    adw::ActionRow {
+                                        set_title: "Continue where you left off",
+                                        #[watch]
+                                        set_subtitle: &model.prefs.last_roots.join(", "),
+                                        set_use_markup: false, // paths may contain markup characters
+                                        set_subtitle_lines: 1,
+                                        set_activatable: true,
+                                        add_suffix = &gtk::Image {
+                                            set_icon_name: Some("go-next-symbolic"),
+                                        },
+                                        connect_activated => AppInput::RestoreSession,
+                                    },
+                                    adw::SwitchRow {
+                                        set_title: "Continue on launch",
+                                        set_active: model.prefs.restore_session,
+                                        connect_active_notify[sender] => move |row| {
+                                            sender.input(AppInput::SetRestoreSession(row.is_active()))
+                                        },
+                                    },
+                                },
+
+                                gtk::ListBox {
+                                    set_selection_mode: gtk::SelectionMode::None,
+                                    set_halign: gtk::Align::Center,
+                                    set_css_classes: &["boxed-list"],
+                                    set_width_request: 420,
                                     #[watch]
                                     set_visible: !model.favs_folders.is_empty(),
 
@@ -160,12 +416,107 @@ impl SimpleComponent for AppModel {
                                         set_hexpand: true,
                                     },
                                 },
+
+                                gtk::Button {
+                                    set_css_classes: &["pill"],
+                                    set_label: "Pick from all favorites",
+                                    set_tooltip_text: Some("Open every favorite folder as a single session"),
+                                    set_halign: gtk::Align::Center,
+                                    set_margin_top: 10,
+                                    #[watch]
+                                    set_visible: model.prefs.favs_folders.len() > 1,
+                                    connect_clicked => AppInput::OpenAllFavorites,
+                                },
+
+                                #[name = "seed_entry"]
+                                gtk::Entry {
+                                    set_halign: gtk::Align::Center,
+                                    set_width_request: 275,
+                                    set_margin_top: 10,
+                                    set_placeholder_text: Some("Shuffle seed (optional)"),
+                                    set_tooltip_text: Some("The next folder opened is shuffled with this seed, the same seed and the same folders give the same order (seeds don't apply to the weighted pick)"),
+                                    set_input_purpose: gtk::InputPurpose::Digits,
+                                    #[watch]
+                                    set_class_active: ("error", model.seed.is_err()),
+                                    connect_changed[sender] => move |entry| {
+                                        sender.input(AppInput::SetSeed(entry.text().to_string()))
+                                    },
+                                },
                             },
 
                             add_named[Some("viewfolder")] = &gtk::Box {
                                 set_orientation: gtk::Orientation::Vertical,
                                 set_halign: gtk::Align::Center,
                                 set_valign: gtk::Align::Center,
+
+                                gtk::Box {
+                                    set_orientation: gtk::Orientation::Horizontal,
+                                    set_spacing: 10,
+
+                                    gtk::Entry {
+                                        set_hexpand: true,
+                                        set_placeholder_text: Some("Filter, e.g. tag:todo -rating<3 images>=20 modified>=2026"),
+                                        set_primary_icon_name: Some("system-search-symbolic"),
+                                        set_secondary_icon_name: Some("edit-clear-symbolic"),
+                                        #[track(model.filter_changed)]
+                                        set_text: model.curr_folder.as_ref().map(|f| f.filter()).unwrap_or_default(),
+                                        #[watch]
+                                        set_class_active: ("error", model.filter_error.is_some()),
+                                        #[watch]
+                                        set_tooltip_text: Some(model.filter_error.as_deref().unwrap_or(filter::FILTER_HELP)),
+                                        connect_activate[sender] => move |entry| {
+                                            sender.input(AppInput::SetFilter(entry.text().to_string()))
+                                        },
+                                        connect_icon_release[sender] => move |_, icon| {
+                                            if icon == gtk::EntryIconPosition::Secondary {
+                                                sender.input(AppInput::SetFilter(String::new()))
+                                            }
+                                        },
+                                    },
+                                    gtk::Label {
+                                        #[watch]
+                                        set_label: &model.filter_count(),
+                                        add_css_class: "dim-label",
+                                    },
+                                    gtk::Button {
+                                        set_icon_name: "view-grid-symbolic",
+                                        set_tooltip_text: Some("Gallery (Ctrl+G)"),
+                                        connect_clicked => AppInput::ToggleGallery,
+                                    },
+                                    gtk::MenuButton {
+                                        set_icon_name: "edit-find-symbolic",
+                                        set_tooltip_text: Some("Search (Ctrl+F)"),
+
+                                        #[wrap(Some)]
+                                        #[name = "search_popover"]
+                                        set_popover = &gtk::Popover {
+                                            gtk::Box {
+                                                set_orientation: gtk::Orientation::Vertical,
+                                                set_spacing: 10,
+
+                                                gtk::SearchEntry {
+                                                    set_placeholder_text: Some("Search by name"),
+                                                    connect_search_changed[sender] => move |entry| {
+                                                        sender.input(AppInput::Search(entry.text().to_string()))
+                                                    },
+                                                    connect_activate => AppInput::SearchActivate,
+                                                },
+                                                gtk::ScrolledWindow {
+                                                    set_min_content_height: 300,
+                                                    set_min_content_width: 400,
+                                                    set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                                                    #[local_ref]
+                                                    search_results_list -> gtk::ListBox {
+                                                        set_selection_mode: gtk::SelectionMode::None,
+                                                        add_css_class: "boxed-list",
+                                                    },
+                                                },
+                                            },
+                                        },
+                                    },
+                                },
+
                                 // layout with a header card with thumbnail and in the side action button and bellow the images
                                 gtk::Box {
                                     set_orientation: gtk::Orientation::Vertical,
@@ -173,22 +524,61 @@ impl SimpleComponent for AppModel {
                                     set_spacing: 10,
                                     set_margin_top: 20,
 
-                                    gtk::Image {
-                                        #[watch]
-                                        set_pixel_size: match model.prefs.app_mode.clone() {
-                                            AppMode::SubFolders | AppMode::Videos => 600,
-                                            AppMode::Images => 860, // highest number before it breaks the app
+                                    gtk::Overlay {
+                                        add_controller: {
+                                            let motion_controller = EventControllerMotion::new();
+
+                                            let sender_enter = sender.clone();
+                                            motion_controller.connect_enter(move |_,_,_| sender_enter.input(AppInput::PreviewVideo(true)));
+
+                                            let sender_leave = sender.clone();
+                                            motion_controller.connect_leave(move |_| sender_leave.input(AppInput::PreviewVideo(false)));
+
+                                            motion_controller
                                         },
-                                        set_size_request: (400, 600),
-                                        set_margin_horizontal: 10,
-                                        set_margin_top: 10,
 
-                                        #[watch]
-                                        set_from_file: match (&model.curr_folder, model.prefs.app_mode.clone()) {
-                                            (Some(f), AppMode::SubFolders) if f.subfolders.get(f.curr).is_some() && f.subfolders.get(f.curr).unwrap().thumbnail.is_some()  => Some(format!("{}/{}", f.subfolders[f.curr].get_path(), f.subfolders[f.curr].thumbnail.as_ref().unwrap())),
-                                            (Some(f), AppMode::Images) => f.images.get(f.curr).map(|i| i.filepath.clone()),
-                                            (Some(f), AppMode::Videos) => f.videos.get(f.curr).and_then(|v| backend::get_video_thumbnail(&v.filepath.clone()).ok()),
-                                            _ => None::<String>,
+                                        gtk::Box {
+                                            set_orientation: gtk::Orientation::Vertical,
+
+                                            gtk::Image {
+                                                #[watch]
+                                                set_pixel_size: match model.curr_kind() {
+                                                    AppMode::SubFolders | AppMode::Videos | AppMode::All => 600,
+                                                    AppMode::Images => 860, // highest number before it breaks the app
+                                                },
+                                                set_size_request: (400, 600),
+                                                set_margin_horizontal: 10,
+                                                set_margin_top: 10,
+
+                                                #[watch]
+                                                set_from_file: match &model.thumbnail.1 {
+                                                    Some(Thumbnail::Ready(thumbnail)) => Some(thumbnail.clone()),
+                                                    _ => None,
+                                                },
+                                                #[watch]
+                                                set_visible: !model.shows_video_fallback(),
+                                            },
+
+                                            gtk::Image {
+                                                set_icon_name: Some(thumbnailer::FALLBACK_ICON),
+                                                set_pixel_size: 256,
+                                                set_size_request: (400, 600),
+                                                set_margin_horizontal: 10,
+                                                set_margin_top: 10,
+                                                add_css_class: "dim-label",
+                                                #[watch]
+                                                set_visible: model.shows_video_fallback(),
+                                            },
+                                        },
+
+                                        add_overlay = &gtk::Video {
+                                            set_autoplay: false,
+                                            set_margin_horizontal: 10,
+                                            set_margin_top: 10,
+                                            #[track(model.item_changed)]
+                                            set_media_stream: model.player.as_ref().map(|p| &p.media),
+                                            #[watch]
+                                            set_visible: model.player.as_ref().is_some_and(|p| p.is_shown()),
                                         },
                                     },
 
@@ -198,6 +588,7 @@ impl SimpleComponent for AppModel {
                                             AppMode::SubFolders => f.subfolders.get(f.curr).map(|sf| sf.name.as_str()).unwrap_or_default(),
                                             AppMode::Images => f.images.get(f.curr).map(|i| i.name.as_str()).unwrap_or_default(),
                                             AppMode::Videos => f.videos.get(f.curr).map(|v| v.name.as_str()).unwrap_or_default(),
+                                            AppMode::All => f.all.get(f.curr).map(|i| i.name()).unwrap_or_default(),
                                         }).unwrap_or(""),
                                         add_css_class: "title-2",
                                         set_margin_horizontal: 10,
@@ -216,6 +607,41 @@ impl SimpleComponent for AppModel {
                                             motion_controller
                                         },
                                     },
+                                    gtk::Label {
+                                        #[watch]
+                                        set_label: &model.curr_origin().unwrap_or_default(),
+                                        #[watch]
+                                        set_visible: model.curr_origin().is_some(),
+                                        add_css_class: "dim-label",
+                                        set_margin_horizontal: 10,
+                                        set_ellipsize: gtk::pango::EllipsizeMode::Middle,
+                                        set_max_width_chars: 50,
+                                    },
+
+                                    gtk::ListBox {
+                                        set_selection_mode: gtk::SelectionMode::None,
+                                        set_css_classes: &["boxed-list"],
+                                        set_margin_horizontal: 10,
+
+                                        adw::EntryRow {
+                                            set_title: "Tags (separated by commas)",
+                                            set_show_apply_button: true,
+                                            #[track(model.item_changed)]
+                                            set_text: &model.curr_meta_text().0,
+                                            connect_apply[sender] => move |row| {
+                                                sender.input(AppInput::SetTags(row.text().to_string()))
+                                            },
+                                        },
+                                        adw::EntryRow {
+                                            set_title: "Note",
+                                            set_show_apply_button: true,
+                                            #[track(model.item_changed)]
+                                            set_text: &model.curr_meta_text().1,
+                                            connect_apply[sender] => move |row| {
+                                                sender.input(AppInput::SetNote(row.text().to_string()))
+                                            },
+                                        },
+                                    },
 
                                     #[name = "popover"]
                                     gtk::Popover {
@@ -228,6 +654,7 @@ impl SimpleComponent for AppModel {
                                                 AppMode::SubFolders => f.subfolders.get(f.curr).map(|sf| sf.name.as_str()).unwrap_or_default(),
                                                 AppMode::Images => f.images.get(f.curr).map(|i| i.name.as_str()).unwrap_or_default(),
                                                 AppMode::Videos => f.videos.get(f.curr).map(|v| v.name.as_str()).unwrap_or_default(),
+                                                AppMode::All => f.all.get(f.curr).map(|i| i.name()).unwrap_or_default(),
                                             }).unwrap_or(""),
                                             set_margin_all: 12,
                                         }
@@ -253,9 +680,221 @@ impl SimpleComponent for AppModel {
                                             set_icon_name: "eye",
                                             connect_clicked => AppInput::OpenImg,
                                         },
-                                    }
+                                        gtk::Button {
+                                            set_css_classes: &["pill", "suggested-action"],
+                                            #[watch]
+                                            set_icon_name: match model.player.as_ref().is_some_and(|p| p.is_playing()) {
+                                                true => "media-playback-pause-symbolic",
+                                                false => "media-playback-start-symbolic",
+                                            },
+                                            set_tooltip_text: Some("Play the video"),
+                                            #[watch]
+                                            set_visible: model.player.as_ref().is_some_and(|p| !p.is_failed()),
+                                            connect_clicked => AppInput::TogglePlay,
+                                        },
+                                        gtk::Button {
+                                            set_css_classes: &["pill", "suggested-action"],
+                                            #[watch]
+                                            set_icon_name: match model.player.as_ref().is_some_and(|p| p.is_muted()) {
+                                                true => "audio-volume-muted-symbolic",
+                                                false => "audio-volume-high-symbolic",
+                                            },
+                                            set_tooltip_text: Some("Mute the video"),
+                                            #[watch]
+                                            set_visible: model.player.as_ref().is_some_and(|p| !p.is_failed()),
+                                            connect_clicked => AppInput::ToggleMuted,
+                                        },
+                                        gtk::Button {
+                                            set_css_classes: &["pill", "suggested-action"],
+                                            set_icon_name: "view-paged-symbolic",
+                                            set_tooltip_text: Some("Browse the images of the subfolder (Ctrl+B)"),
+                                            #[watch]
+                                            set_visible: model.curr_kind() == AppMode::SubFolders,
+                                            connect_clicked => AppInput::ToggleBrowser,
+                                        },
+                                    },
+
+                                    gtk::Expander {
+                                        set_label: Some("Details"),
+                                        set_margin_horizontal: 10,
+                                        set_margin_bottom: 10,
+
+                                        gtk::Box {
+                                            set_orientation: gtk::Orientation::Vertical,
+                                            set_spacing: 10,
+                                            set_margin_top: 10,
+
+                                            gtk::ListBox {
+                                                set_selection_mode: gtk::SelectionMode::None,
+                                                set_css_classes: &["boxed-list"],
+
+                                                adw::SwitchRow {
+                                                    set_title: "Weighted pick",
+                                                    set_subtitle: "Items not shown for a long time and the best rated ones come up more often",
+                                                    #[watch]
+                                                    #[block_signal(pick_mode_handler)]
+                                                    set_active: model.curr_folder.as_ref().map(|f| f.pick_mode() == PickMode::Weighted).unwrap_or(false),
+                                                    connect_active_notify[sender] => move |row| {
+                                                        sender.input(AppInput::SetPickMode(match row.is_active() {
+                                                            true => PickMode::Weighted,
+                                                            false => PickMode::Shuffle,
+                                                        }))
+                                                    } @pick_mode_handler,
+                                                },
+                                                adw::SwitchRow {
+                                                    set_title: "Favor bigger subfolders",
+                                                    set_subtitle: "Subfolders with more images come up more often",
+                                                    #[watch]
+                                                    set_sensitive: model.curr_folder.as_ref().map(|f| f.pick_mode() == PickMode::Weighted).unwrap_or(false),
+                                                    #[watch]
+                                                    #[block_signal(item_count_handler)]
+                                                    set_active: model.curr_folder.as_ref().map(|f| f.favor_item_count()).unwrap_or(false),
+                                                    connect_active_notify[sender] => move |row| {
+                                                        sender.input(AppInput::SetFavorItemCount(row.is_active()))
+                                                    } @item_count_handler,
+                                                },
+                                                adw::SpinRow::with_range(1.0, 10.0, 1.0) {
+                                                    set_title: "Scan depth",
+                                                    set_subtitle: "How many levels of nested folders are scanned",
+                                                    #[watch]
+                                                    #[block_signal(scan_depth_handler)]
+                                                    set_value: model.shown_scan_depth() as f64,
+                                                    connect_value_notify[sender] => move |row| {
+                                                        sender.input(AppInput::SetScanDepth(row.value() as usize))
+                                                    } @scan_depth_handler,
+                                                },
+                                                adw::SwitchRow {
+                                                    set_title: "Only leaf folders",
+                                                    set_subtitle: "Only the deepest folders containing medias can be picked",
+                                                    #[watch]
+                                                    #[block_signal(leaf_folders_handler)]
+                                                    set_active: model.curr_folder.as_ref().map(|f| f.scan_options().leaf_folders).unwrap_or(false),
+                                                    connect_active_notify[sender] => move |row| {
+                                                        sender.input(AppInput::SetLeafFolders(row.is_active()))
+                                                    } @leaf_folders_handler,
+                                                },
+                                                adw::ActionRow {
+                                                    set_title: "Shuffle seed",
+                                                    set_subtitle_selectable: true,
+                                                    #[watch]
+                                                    set_subtitle: &model.curr_folder.as_ref().map(|f| f.seed().to_string()).unwrap_or_default(),
+                                                    set_tooltip_text: Some("Share it to get the same order, it can be entered when opening a folder. Seeds only apply to the shuffle, the weighted pick is always random"),
+                                                    // the weighted pick depends on when the items were shown, a seed can't reproduce it
+                                                    #[watch]
+                                                    set_visible: model.curr_folder.as_ref().map(|f| f.pick_mode() == PickMode::Shuffle).unwrap_or(false),
+                                                },
+                                            },
+
+                                            gtk::Label {
+                                                #[watch]
+                                                set_label: &model.pick_details(),
+                                                set_halign: gtk::Align::Start,
+                                                set_wrap: true,
+                                                add_css_class: "dim-label",
+                                            },
+                                        },
+                                    },
                                 }
-                            }
+                            },
+
+                            add_named[Some("gallery")] = &gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+                                set_spacing: 10,
+
+                                gtk::Box {
+                                    set_orientation: gtk::Orientation::Horizontal,
+                                    set_spacing: 10,
+
+                                    gtk::Button {
+                                        set_icon_name: "go-previous-symbolic",
+                                        set_tooltip_text: Some("Back to the current item (Ctrl+G)"),
+                                        connect_clicked => AppInput::ToggleGallery,
+                                    },
+                                    gtk::Label {
+                                        #[watch]
+                                        set_label: &model.filter_count(),
+                                        add_css_class: "dim-label",
+                                    },
+                                },
+
+                                gtk::ScrolledWindow {
+                                    set_vexpand: true,
+                                    set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                                    #[local_ref]
+                                    gallery_view -> gtk::GridView {
+                                        set_single_click_activate: true,
+                                        set_max_columns: 12,
+                                        connect_activate[sender] => move |_, position| {
+                                            sender.input(AppInput::GalleryActivate(position))
+                                        },
+                                    },
+                                },
+                            },
+
+                            #[name = "browse_page"]
+                            add_named[Some("browse")] = &gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+                                set_spacing: 10,
+                                set_focusable: true,
+
+                                add_controller = gtk::EventControllerKey {
+                                    connect_key_pressed[sender] => move |_, key, _, _| {
+                                        let input = match key {
+                                            gtk::gdk::Key::Page_Up => AppInput::BrowseBy(-BROWSER_PAGE),
+                                            gtk::gdk::Key::Page_Down => AppInput::BrowseBy(BROWSER_PAGE),
+                                            gtk::gdk::Key::Home => AppInput::BrowseTo(0),
+                                            gtk::gdk::Key::End => AppInput::BrowseTo(usize::MAX),
+                                            gtk::gdk::Key::Escape => AppInput::ToggleBrowser,
+                                            _ => return glib::Propagation::Proceed,
+                                        };
+                                        sender.input(input);
+                                        glib::Propagation::Stop
+                                    },
+                                },
+
+                                gtk::Box {
+                                    set_orientation: gtk::Orientation::Horizontal,
+                                    set_spacing: 10,
+
+                                    gtk::Button {
+                                        set_icon_name: "go-previous-symbolic",
+                                        set_tooltip_text: Some("Back to the current item (Escape)"),
+                                        connect_clicked => AppInput::ToggleBrowser,
+                                    },
+                                    gtk::Label {
+                                        #[watch]
+                                        set_label: &model.browser_position(),
+                                        set_hexpand: true,
+                                        set_ellipsize: gtk::pango::EllipsizeMode::Middle,
+                                        add_css_class: "title-4",
+                                    },
+                                    gtk::Button {
+                                        set_icon_name: "eye",
+                                        set_tooltip_text: Some("Open in the default image viewer (Up)"),
+                                        connect_clicked => AppInput::OpenImg,
+                                    },
+                                },
+
+                                gtk::Picture {
+                                    set_vexpand: true,
+                                    set_hexpand: true,
+                                    set_content_fit: gtk::ContentFit::Contain,
+                                    #[track(model.browser_changed)]
+                                    set_paintable: model.browser_image.as_ref(),
+                                },
+
+                                gtk::ScrolledWindow {
+                                    set_vscrollbar_policy: gtk::PolicyType::Never,
+                                    set_min_content_height: gallery::GALLERY_THUMBNAIL_SIZE + 50,
+
+                                    #[local_ref]
+                                    filmstrip_view -> gtk::GridView {
+                                        set_orientation: gtk::Orientation::Horizontal,
+                                        set_max_columns: 1,
+                                    },
+                                },
+                            },
                         }
                     }
                 }
@@ -264,7 +903,7 @@ impl SimpleComponent for AppModel {
     }
 
     fn init(
-        prefs: Self::Init,
+        (prefs, load_error): Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
@@ -274,9 +913,11 @@ impl SimpleComponent for AppModel {
             .forward(sender.input_sender(), |msg| match msg {
                 HeaderOutput::About => AppInput::OpenAbout,
                 HeaderOutput::Shortcuts => AppInput::OpenShortcuts,
+                HeaderOutput::ThumbnailCache => AppInput::OpenThumbnailCache,
                 HeaderOutput::NewDir => AppInput::ChooseFolder,
                 HeaderOutput::SetBookmarked(b) => AppInput::SetBookmarked(b),
                 HeaderOutput::ChangeAppMode(new_app_mode) => AppInput::ChangeAppMode(new_app_mode),
+                HeaderOutput::SetRating(rating) => AppInput::SetRating(rating),
             });
         let about_page = AboutPageModel::builder()
             .transient_for(&root)
@@ -288,40 +929,115 @@ impl SimpleComponent for AppModel {
         let mut favs_folders = FactoryVecDeque::builder()
             .launch(adw::ExpanderRow::default())
             .forward(sender.input_sender(), |msg| match msg {
-                FavFolderOutput::ChoseFavFolder(path) => AppInput::AddFolder(path),
+                FavFolderOutput::ChoseFavFolder(path) => AppInput::OpenFavorite(path),
+                FavFolderOutput::Remove(path) => AppInput::RemoveFavorite(path),
+                FavFolderOutput::Rename(path, alias) => AppInput::RenameFavorite(path, alias),
+                FavFolderOutput::SetAppMode(path, app_mode) => {
+                    AppInput::SetFavoriteMode(path, app_mode)
+                }
+                FavFolderOutput::Move(path, to) => AppInput::MoveFavorite(path, to),
             });
-        for fpath in &prefs.favs_folders {
-            favs_folders.guard().push_back(fpath.to_owned()); // set init value
+        for fav in &prefs.favs_folders {
+            favs_folders.guard().push_back(fav.clone()); // set init value
         }
+        let search_results = FactoryVecDeque::builder()
+            .launch(gtk::ListBox::default())
+            .forward(sender.input_sender(), |msg| match msg {
+                SearchResultOutput::Chose(key) => AppInput::JumpTo(key),
+            });
 
         // define default model
         let mut model = AppModel {
             prefs,
             current_page: AppPages::ChooseFolder,
             curr_folder: None,
+            thumbnail: (None, None),
+            scan: None,
+            scan_id: 0,
+            watchers: vec![],
+            pending_changes: None,
+            pending_scan_depth: None,
+            scan_depth_steps: 0,
+            deck_flush_steps: 0,
+            shown_item: None,
+            shown_rating: None,
+            restoring: false,
+            item_changed: false,
+            filter_changed: false,
+            filter_error: None,
+            seed: Ok(None),
+            browser: None,
+            browser_changed: false,
+            browser_image: None,
+            player: None,
+            dbus: None,
 
             // components
             title_popover: gtk::Popover::default(),
+            search_popover: gtk::Popover::default(),
+            seed_entry: gtk::Entry::default(),
+            browse_page: gtk::Box::default(),
             header,
             about_page,
             toaster: Toaster::default(),
             favs_folders,
+            search_results,
+            gallery: TypedGridView::new(),
+            filmstrip: TypedGridView::new(),
             shortcuts_window,
         };
 
         // inject to view!
         let toast_overlay = model.toaster.overlay_widget();
         let favs_folders_factory = model.favs_folders.widget();
+        let search_results_list = model.search_results.widget();
+        let gallery_view = &model.gallery.view;
+        let filmstrip_view = &model.filmstrip.view;
+        // the selected image of the filmstrip is the one shown
+        model.filmstrip.selection_model.set_autoselect(false);
+        let filmstrip_sender = sender.clone();
+        model
+            .filmstrip
+            .selection_model
+            .connect_selected_notify(move |selection| {
+                if selection.selected() != gtk::INVALID_LIST_POSITION {
+                    filmstrip_sender.input(AppInput::BrowseTo(selection.selected() as usize))
+                }
+            });
 
         let widgets = view_output!();
         model.title_popover = widgets.popover.clone();
+        model.search_popover = widgets.search_popover.clone();
+        model.seed_entry = widgets.seed_entry.clone();
+        model.browse_page = widgets.browse_page.clone();
         // actions
         init_app_actions!(sender, widgets);
+        model.dbus = DBusService::register(
+            relm4::main_application().upcast_ref(),
+            sender.input_sender().clone(),
+        );
+
+        if let Some(err) = load_error {
+            push_error!(err, sender);
+        }
+        // the app opens in the last used mode
+        let _ = model
+            .header
+            .sender()
+            .send(HeaderInput::ChangeToKnownAppMode(
+                model.prefs.app_mode.clone(),
+            ));
+        if model.prefs.restore_session && !model.prefs.last_roots.is_empty() {
+            sender.input(AppInput::RestoreSession);
+        }
 
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        self.item_changed = false;
+        self.filter_changed = false;
+        self.browser_changed = false;
         match message {
             AppInput::OpenAbout => {
                 if self.about_page.sender().send(AboutInput::Show).is_err() {
@@ -329,26 +1045,143 @@ impl SimpleComponent for AppModel {
                 }
             }
             AppInput::OpenShortcuts => self.shortcuts_window.present(),
+            AppInput::OpenSearch => {
+                if matches!(self.current_page, AppPages::ViewFolder) {
+                    self.search_popover.popup();
+                }
+            }
+            AppInput::Search(query) => {
+                let hits = match (&self.curr_folder, query.trim().is_empty()) {
+                    (Some(folder), false) => {
+                        folder.search(self.prefs.app_mode.clone(), &query, SEARCH_RESULTS_LIMIT)
+                    }
+                    _ => vec![],
+                };
+                let mut results = self.search_results.guard();
+                results.clear();
+                for hit in hits {
+                    results.push_back(hit);
+                }
+            }
+            AppInput::SearchActivate => {
+                if let Some(result) = self.search_results.get(0) {
+                    sender.input(AppInput::JumpTo(result.hit.key.clone()));
+                }
+            }
+            AppInput::JumpTo(key) => {
+                if let Some(folder) = self.curr_folder.as_mut() {
+                    if !folder.jump_to(self.prefs.app_mode.clone(), &key) {
+                        push_toast!("This item is no longer in the folder", 2, sender);
+                    }
+                }
+                self.search_popover.popdown();
+                self.schedule_deck_flush(&sender);
+            }
+            AppInput::ToggleGallery => match self.current_page {
+                AppPages::ViewFolder => {
+                    self.current_page = AppPages::Gallery;
+                    self.fill_gallery();
+                }
+                AppPages::Gallery => self.current_page = AppPages::ViewFolder,
+                AppPages::ChooseFolder | AppPages::Browse => {}
+            },
+            AppInput::GalleryActivate(position) => {
+                if let Some(item) = self.gallery.get(position) {
+                    let key = item.borrow().entry.key.clone();
+                    sender.input(AppInput::JumpTo(key));
+                }
+                self.current_page = AppPages::ViewFolder;
+            }
+            AppInput::ToggleBrowser => match self.current_page {
+                AppPages::ViewFolder => self.open_browser(&sender),
+                AppPages::Browse => self.current_page = AppPages::ViewFolder,
+                _ => {}
+            },
+            AppInput::NextItem if matches!(self.current_page, AppPages::Browse) => {
+                self.browse(|b| b.page(1), &sender)
+            }
+            AppInput::PrevItem if matches!(self.current_page, AppPages::Browse) => {
+                self.browse(|b| b.page(-1), &sender)
+            }
+            AppInput::BrowseBy(delta) => self.browse(|b| b.page(delta), &sender),
+            AppInput::BrowseTo(index) => self.browse(|b| b.select(index), &sender),
+            AppInput::OpenImg if matches!(self.current_page, AppPages::Browse) => {
+                if let Some(image) = self.browser.as_ref().and_then(|b| b.curr_image()) {
+                    let _ = opener::open(image);
+                }
+            }
+            AppInput::TogglePlay => {
+                if let Some(player) = self.player.as_mut() {
+                    player.toggle_play();
+                }
+            }
+            AppInput::ToggleMuted => {
+                if let Some(player) = self.player.as_mut() {
+                    player.toggle_muted();
+                }
+            }
+            AppInput::PreviewVideo(hovered) => {
+                if let Some(player) = self.player.as_mut() {
+                    player.set_preview(hovered);
+                }
+            }
+            AppInput::PlayerChanged => {
+                if let Some(player) = self.player.as_mut() {
+                    player.sync();
+                }
+            }
+            AppInput::PlayerFailed(path, reason) => {
+                if let Some(player) = self.player.as_mut().filter(|p| p.filepath == path) {
+                    if !player.is_failed() {
+                        player.fail();
+                        push_error!(AppError::Playback { path, reason }, sender);
+                    }
+                }
+            }
+            AppInput::OpenThumbnailCache => match thumb_cache::size() {
+                Ok(size) => {
+                    let sender = sender.clone();
+                    show_cache_dialog(
+                        &thumb_cache::format_size(size),
+                        &thumb_cache::format_size(thumb_cache::MAX_CACHE_SIZE),
+                        move || sender.input(AppInput::ClearThumbnailCache),
+                    );
+                }
+                Err(err) => push_error!(err, sender),
+            },
+            AppInput::ClearThumbnailCache => {
+                let result = thumb_cache::clear();
+                // the current video thumbnail may be gone too
+                self.thumbnail = (None, None);
+                match result {
+                    Ok(()) => push_toast!("Thumbnail cache cleared", 2, sender),
+                    Err(err) => push_error!(err, sender),
+                }
+            }
             AppInput::ChooseFolder => {
                 let dialog = gtk::FileDialog::builder()
-                    .title("Choose folder")
+                    .title("Choose folders (several ones are picked from together)")
                     .initial_folder({
                         let pics_raw = dirs::picture_dir().unwrap();
                         let pics = pics_raw.to_str().unwrap();
                         &gtk::gio::File::for_path(pics)
                     })
                     .build();
-                dialog.select_folder(
+                let sender = sender.clone();
+                dialog.select_multiple_folders(
                     None::<&gtk::Window>,
                     None::<&gtk::gio::Cancellable>,
                     move |result| match result {
-                        Ok(f) => {
-                            if let Some(path) =
-                                f.path().and_then(|p| p.to_str().map(|s| s.to_string()))
-                            {
-                                sender.input(AppInput::AddFolder(path))
-                            } else {
-                                push_toast!("Failed to choose folder", 2, sender)
+                        Ok(files) => {
+                            let paths = (0..files.n_items())
+                                .filter_map(|i| files.item(i).and_downcast::<gtk::gio::File>())
+                                .filter_map(|f| {
+                                    f.path().and_then(|p| p.to_str().map(|s| s.to_string()))
+                                })
+                                .collect::<Vec<_>>();
+                            match paths.is_empty() {
+                                true => push_toast!("Failed to choose folder", 2, sender),
+                                false => sender.input(AppInput::AddFolders(paths)),
                             }
                         }
                         _ => push_toast!("Failed to choose folder", 2, sender),
@@ -357,58 +1190,140 @@ impl SimpleComponent for AppModel {
             }
             AppInput::ChangeAppMode(new_app_mode) => {
                 if let Some(folder) = self.curr_folder.as_mut() {
-                    folder.reset_curr_index();
+                    folder.reset_curr_index(new_app_mode.clone());
                 }
                 self.prefs.app_mode = new_app_mode;
+                self.fill_gallery();
+                if matches!(self.current_page, AppPages::Browse) {
+                    self.current_page = AppPages::ViewFolder;
+                }
             }
-            AppInput::AddFolder(path) => {
-                let (folder, rec_app_mode) = match AppFolderManager::set_folder(path) {
-                    Ok(f) => f,
-                    Err(_) => {
-                        return push_toast!(
-                            "Failed to gather information about this folder",
-                            4,
-                            sender
-                        )
-                    }
-                };
-                let _ = self
-                    .header
-                    .sender()
-                    .send(HeaderInput::ShowBookmarkBtn(true));
-                let _ = self.header.sender().send(HeaderInput::SetBookmark(
-                    self.prefs
-                        .favs_folders
-                        .iter()
-                        .find(|n| n == &&folder.root_path)
-                        .map(|_| true)
-                        .unwrap_or(false),
-                ));
-                self.curr_folder = Some(folder);
-
-                // sender.input(AppInput::NextItem); // before UI update to prevent user to see "blank" screen
-                if let Some(new_app_mode) = rec_app_mode {
-                    sender.input(AppInput::ChangeAppMode(new_app_mode.clone()));
+            AppInput::AddFolders(roots) => self.open_folders(roots, false, &sender),
+            AppInput::OpenRoot(path, app_mode) => {
+                if let Some(app_mode) = app_mode {
+                    self.prefs.app_mode = app_mode.clone();
                     let _ = self
                         .header
                         .sender()
-                        .send(HeaderInput::ChangeToKnownAppMode(new_app_mode));
+                        .send(HeaderInput::ChangeToKnownAppMode(app_mode));
+                }
+                sender.input(AppInput::AddFolders(vec![path]));
+            }
+            AppInput::OpenAllFavorites => {
+                // the missing folders would only fail the scan
+                let roots = self
+                    .prefs
+                    .favs_folders
+                    .iter()
+                    .filter(|f| !f.is_missing())
+                    .map(|f| f.path.clone())
+                    .collect();
+                sender.input(AppInput::AddFolders(roots));
+            }
+            AppInput::RestoreSession => {
+                let roots = self.prefs.last_roots.clone();
+                self.open_folders(roots, true, &sender);
+            }
+            AppInput::SetRestoreSession(restore) => {
+                self.prefs.restore_session = restore;
+                if let Err(err) = self.prefs.save() {
+                    push_error!(err, sender);
+                }
+            }
+            AppInput::OpenFavorite(path) => {
+                let app_mode = self.prefs.favorite(&path).and_then(|f| f.app_mode.clone());
+                sender.input(AppInput::OpenRoot(path, app_mode));
+            }
+            AppInput::RemoveFavorite(path) => {
+                if self.prefs.remove_favorite(&path) {
+                    self.favorites_changed(&sender);
+                    push_toast!("Successfully unbookmarked", 2, sender);
+                }
+            }
+            AppInput::RenameFavorite(path, alias) => {
+                if let Some(fav) = self.prefs.favorite_mut(&path) {
+                    let alias = alias.trim();
+                    fav.alias = (!alias.is_empty()).then(|| alias.to_string());
+                    self.favorites_changed(&sender);
                 }
-                sender.input(AppInput::SwitchPage(AppPages::ViewFolder));
+            }
+            AppInput::SetFavoriteMode(path, app_mode) => {
+                if let Some(fav) = self.prefs.favorite_mut(&path) {
+                    fav.app_mode = app_mode;
+                    self.favorites_changed(&sender);
+                }
+            }
+            AppInput::MoveFavorite(path, to) => {
+                self.prefs.move_favorite(&path, to);
+                self.favorites_changed(&sender);
+            }
+            AppInput::CancelScan => self.cancel_scan(),
+            AppInput::FolderChanged(root, event) => {
+                let folder = match self.curr_folder.as_mut() {
+                    Some(f) => f,
+                    None => return,
+                };
+                // the changes are applied once they settle down (e.g. while files are being copied)
+                if self.pending_changes.is_none() {
+                    let sender = sender.clone();
+                    glib::timeout_add_local_once(Duration::from_secs(1), move || {
+                        sender.input(AppInput::FlushFolderChanges)
+                    });
+                }
+                let (created, removed) = self.pending_changes.get_or_insert_with(Default::default);
+                match event {
+                    WatchEvent::Created(rel_path) => created.push((root, rel_path)),
+                    WatchEvent::Deleted(rel_path) => {
+                        *removed +=
+                            folder.remove_items(&root, &rel_path, self.prefs.app_mode.clone());
+                        if let Some(watcher) =
+                            self.watchers.iter_mut().find(|w| w.root_path() == root)
+                        {
+                            watcher.unwatch(&rel_path);
+                        }
+                    }
+                }
+            }
+            AppInput::FlushFolderChanges => {
+                let (folder, (created, removed)) =
+                    match (self.curr_folder.as_ref(), self.pending_changes.take()) {
+                        (Some(f), Some(changes)) => (f, changes),
+                        _ => return,
+                    };
+                let (scan_id, options) = (self.scan_id, folder.scan_options());
+                sender.spawn_command(move |out| {
+                    let (mut items, mut new_dirs) = (ScannedItems::default(), vec![]);
+                    for (root, rel_path) in created {
+                        items.extend(scanner::scan_path(&root, &rel_path, &options));
+                        let dirs = scanner::dirs_to_watch(&root, &rel_path, &options);
+                        new_dirs.extend(dirs.into_iter().map(|dir| (root.clone(), dir)));
+                    }
+                    let _ = out.send(AppCommand::FolderChanged(scan_id, items, new_dirs, removed));
+                });
             }
             AppInput::NextItem => {
                 if let Some(folder) = self.curr_folder.as_mut() {
-                    if folder.next(self.prefs.app_mode.clone()).is_err() {
-                        sender.input(AppInput::SwitchPage(AppPages::ChooseFolder));
-                        self.curr_folder = None;
+                    match folder.next(self.prefs.app_mode.clone()) {
+                        Ok(true) => {
+                            push_toast!("Everything has been seen, starting a new round", 3, sender)
+                        }
+                        Ok(false) => {}
+                        Err(_) if folder.has_items(self.prefs.app_mode.clone()) => {
+                            push_toast!("No item matches the filter", 2, sender)
+                        }
+                        Err(_) => {
+                            sender.input(AppInput::SwitchPage(AppPages::ChooseFolder));
+                            self.close_folder();
+                        }
                     }
                 }
+                self.schedule_deck_flush(&sender);
             }
             AppInput::PrevItem => {
                 if let Some(folder) = self.curr_folder.as_mut() {
                     if folder.prev(self.prefs.app_mode.clone()).is_err() {
                         sender.input(AppInput::SwitchPage(AppPages::ChooseFolder));
-                        self.curr_folder = None;
+                        self.close_folder();
                     }
                 }
             }
@@ -430,6 +1345,11 @@ impl SimpleComponent for AppModel {
                                 v.open_image();
                             }
                         }
+                        AppMode::All => {
+                            if let Some(i) = folder.all.get(folder.curr) {
+                                i.open_image();
+                            }
+                        }
                     };
                 }
             }
@@ -451,6 +1371,11 @@ impl SimpleComponent for AppModel {
                                 v.open_dir();
                             }
                         }
+                        AppMode::All => {
+                            if let Some(i) = folder.all.get(folder.curr) {
+                                i.open_dir();
+                            }
+                        }
                     };
                 }
             }
@@ -460,8 +1385,16 @@ impl SimpleComponent for AppModel {
             },
             AppInput::SetBookmarked(bookmarked) => {
                 if let Some(folder) = &self.curr_folder {
+                    // every root of a multi-root session is bookmarked
+                    let mut changed = false;
+                    for root in &folder.roots {
+                        changed |= match bookmarked {
+                            true => self.prefs.add_favorite(root),
+                            false => self.prefs.remove_favorite(root),
+                        };
+                    }
                     match bookmarked {
-                        true => match self.prefs.favs_folders.insert(folder.root_path.to_owned()) {
+                        true => match changed {
                             true => push_toast!("Successfully bookmarked", 2, sender),
                             false => {
                                 push_toast!("Failed to bookmark folder", 2, sender);
@@ -471,7 +1404,7 @@ impl SimpleComponent for AppModel {
                                     .send(HeaderInput::ToogleBookmark(false));
                             }
                         },
-                        false => match self.prefs.favs_folders.remove(&folder.root_path) {
+                        false => match changed {
                             true => push_toast!("Successfully unbookmarked", 2, sender),
                             false => {
                                 push_toast!("Failed to unbookmark folder", 2, sender);
@@ -482,19 +1415,765 @@ impl SimpleComponent for AppModel {
                             }
                         },
                     }
-                    let _ = self.prefs.save();
+                    self.favorites_changed(&sender);
                 }
             }
-            AppInput::PushToast((text, timeout)) => {
+            AppInput::PushToast((text, timeout, error)) => {
                 let toast = adw::Toast::builder()
-                    .title(text)
-                    .button_label("Cancel")
+                    .title(&text)
+                    .use_markup(false) // paths may contain markup characters
                     .timeout(timeout.as_secs() as u32)
                     .build();
-                toast.connect_button_clicked(move |this| this.dismiss());
+                match error {
+                    Some(err) => {
+                        toast.set_button_label(Some("Details"));
+                        let details = err.details();
+                        toast.connect_button_clicked(move |_| show_error_dialog(&text, &details));
+                    }
+                    None => {
+                        toast.set_button_label(Some("Cancel"));
+                        toast.connect_button_clicked(move |this| this.dismiss());
+                    }
+                }
                 self.toaster.add_toast(toast);
             }
             AppInput::SwitchPage(page) => self.current_page = page,
+            AppInput::SetPickMode(pick_mode) => {
+                if let Some(folder) = self.curr_folder.as_mut() {
+                    folder.set_pick_mode(pick_mode);
+                }
+            }
+            AppInput::SetFavorItemCount(favor) => {
+                if let Some(folder) = self.curr_folder.as_mut() {
+                    folder.set_favor_item_count(favor);
+                }
+            }
+            AppInput::SetScanDepth(max_depth) => {
+                let folder = match self.curr_folder.as_ref() {
+                    Some(f) => f,
+                    None => return,
+                };
+                // each step of the spin row would rescan the whole folder
+                self.pending_scan_depth = Some((folder.root_path.clone(), max_depth));
+                self.scan_depth_steps += 1;
+                let (sender, steps) = (sender.clone(), self.scan_depth_steps);
+                glib::timeout_add_local_once(Duration::from_millis(800), move || {
+                    sender.input(AppInput::ApplyScanDepth(steps))
+                });
+            }
+            AppInput::ApplyScanDepth(steps) if steps == self.scan_depth_steps => {
+                let (folder, (root_path, max_depth)) =
+                    match (self.curr_folder.as_mut(), self.pending_scan_depth.take()) {
+                        (Some(f), Some(pending)) => (f, pending),
+                        _ => return,
+                    };
+                if folder.root_path != root_path || folder.scan_options().max_depth == max_depth {
+                    return;
+                }
+                folder.set_scan_options(ScanOptions {
+                    max_depth,
+                    ..folder.scan_options()
+                });
+                // rescan
+                sender.input(AppInput::AddFolders(folder.roots.clone()));
+            }
+            AppInput::ApplyScanDepth(_) => {} // changed again since
+            AppInput::FlushDeck(steps) if steps == self.deck_flush_steps => {
+                if let Some(Err(err)) = self.curr_folder.as_mut().map(|f| f.flush_deck()) {
+                    push_error!(err, sender);
+                }
+            }
+            AppInput::FlushDeck(_) => {}
+            AppInput::SetLeafFolders(leaf_folders) => {
+                if let Some(folder) = self.curr_folder.as_mut() {
+                    folder.set_scan_options(ScanOptions {
+                        leaf_folders,
+                        ..folder.scan_options()
+                    });
+                    // rescan
+                    sender.input(AppInput::AddFolders(folder.roots.clone()));
+                }
+            }
+            AppInput::SetRating(rating) => self.edit_curr_meta(&sender, |m| m.rating = rating),
+            AppInput::SetTags(tags) => {
+                self.edit_curr_meta(&sender, |m| m.tags = library::parse_tags(&tags))
+            }
+            AppInput::SetNote(note) => {
+                self.edit_curr_meta(&sender, |m| m.note = note.trim().to_string())
+            }
+            AppInput::SetFilter(filter) => {
+                if let Some(folder) = self.curr_folder.as_mut() {
+                    match folder.set_filter(&filter, self.prefs.app_mode.clone()) {
+                        Ok(()) => {
+                            self.filter_changed = true;
+                            self.filter_error = None;
+                            self.fill_gallery();
+                        }
+                        Err(err) => self.filter_error = Some(err.to_string()),
+                    }
+                }
+            }
+            AppInput::SetSeed(seed) => {
+                self.seed = match seed.trim() {
+                    "" => Ok(None),
+                    seed => seed.parse().map(Some).map_err(|_| ()),
+                }
+            }
+        };
+        self.refresh_thumbnail(&sender);
+        self.refresh_item_meta();
+        self.refresh_player(&sender);
+    }
+
+    fn shutdown(&mut self, widgets: &mut Self::Widgets, _output: relm4::Sender<Self::Output>) {
+        // the window is reopened as it was left, the default size is the one before maximizing
+        let (width, height) = widgets.main_window.default_size();
+        self.prefs.window = WindowState {
+            width,
+            height,
+            maximized: widgets.main_window.is_maximized(),
         };
+        self.save_session();
+        let _ = self.prefs.save();
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        self.item_changed = false;
+        self.browser_changed = false;
+        match message {
+            AppCommand::ScanUpdate(scan_id, items, progress) if scan_id == self.scan_id => {
+                if let Some((_, p)) = self.scan.as_mut() {
+                    *p = progress;
+                }
+                if let Some(folder) = self.curr_folder.as_mut() {
+                    let app_mode = self.prefs.app_mode.clone();
+                    let was_empty = folder.is_empty(app_mode.clone());
+                    folder.add_items(items, app_mode.clone());
+                    if was_empty && !folder.is_empty(app_mode.clone()) {
+                        folder.reset_curr_index(app_mode.clone());
+                    }
+                    // show the first items without waiting for the end of the scan, even if none of them match
+                    // the filter so that it can be changed
+                    if folder.has_items(app_mode) {
+                        self.current_page = AppPages::ViewFolder;
+                    }
+                }
+            }
+            AppCommand::ScanDone(scan_id, result) if scan_id == self.scan_id => {
+                self.scan = None;
+                self.finish_scan(result, &sender);
+                self.fill_gallery();
+            }
+            AppCommand::FolderChanged(scan_id, items, new_dirs, removed)
+                if scan_id == self.scan_id =>
+            {
+                let folder = match self.curr_folder.as_mut() {
+                    Some(f) => f,
+                    None => return,
+                };
+                let app_mode = self.prefs.app_mode.clone();
+                let was_empty = folder.is_empty(app_mode.clone());
+                let added = folder.insert_new_items(items, app_mode.clone());
+                if was_empty && !folder.is_empty(app_mode.clone()) {
+                    folder.reset_curr_index(app_mode);
+                }
+                for watcher in self.watchers.iter_mut() {
+                    watcher.watch(folder.watched_dirs(watcher.root_path()));
+                    // the new directories may be empty for now
+                    let root = watcher.root_path().to_string();
+                    watcher.watch(
+                        new_dirs
+                            .iter()
+                            .filter(|(r, _)| *r == root)
+                            .map(|(_, d)| d.clone()),
+                    );
+                }
+                self.fill_gallery();
+
+                match (added, removed) {
+                    (0, 0) => {}
+                    (a, 0) => push_toast!(format!("{a} new items in this folder"), 2, sender),
+                    (0, r) => push_toast!(format!("{r} items removed from this folder"), 2, sender),
+                    (a, r) => push_toast!(
+                        format!("{a} new and {r} removed items in this folder"),
+                        2,
+                        sender
+                    ),
+                }
+            }
+            AppCommand::ScanError(scan_id, err) if scan_id == self.scan_id => {
+                push_error!(err, sender)
+            }
+            AppCommand::ItemThumbnail(source, result)
+                if self.thumbnail.0.as_ref() == Some(&source) =>
+            {
+                self.thumbnail.1 = Some(match result {
+                    Ok(thumbnail) => Thumbnail::Ready(thumbnail),
+                    Err(err) => {
+                        push_error!(err, sender);
+                        Thumbnail::Fallback
+                    }
+                });
+            }
+            AppCommand::BrowserImage(image, texture)
+                if self.browser.as_ref().and_then(|b| b.curr_image()).as_ref() == Some(&image) =>
+            {
+                self.browser_image = texture;
+                self.browser_changed = true;
+            }
+            _ => {} // outdated scan, thumbnail or image
+        }
+        self.refresh_thumbnail(&sender);
+        self.refresh_item_meta();
+        self.refresh_player(&sender);
+    }
+}
+
+impl AppModel {
+    /// save the favorites and show them again, the bookmark button follows the current roots
+    fn favorites_changed(&mut self, sender: &ComponentSender<Self>) {
+        if let Err(err) = self.prefs.save() {
+            push_error!(err, sender);
+        }
+        let mut favs_folders = self.favs_folders.guard();
+        favs_folders.clear();
+        for fav in &self.prefs.favs_folders {
+            favs_folders.push_back(fav.clone());
+        }
+        drop(favs_folders);
+
+        if let Some(folder) = &self.curr_folder {
+            let bookmarked = folder.roots.iter().all(|root| self.prefs.is_favorite(root));
+            let _ = self
+                .header
+                .sender()
+                .send(HeaderInput::SetBookmark(bookmarked));
+        }
+    }
+
+    fn cancel_scan(&mut self) {
+        if let Some((cancel, _)) = self.scan.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn close_folder(&mut self) {
+        self.save_session();
+        self.cancel_scan();
+        self.watchers.clear();
+        self.pending_changes = None;
+        self.curr_folder = None;
+    }
+
+    /// open a session of one or several roots, `restore` continues the last session of the folder once it's
+    /// scanned
+    fn open_folders(&mut self, roots: Vec<String>, restore: bool, sender: &ComponentSender<Self>) {
+        self.save_session();
+        self.cancel_scan();
+        self.watchers.clear();
+        self.pending_changes = None;
+        let mut folder = AppFolderManager::new(roots);
+        self.restoring = restore;
+        // the session is continued in the mode it was left in
+        if let Some(app_mode) = folder.session_mode().filter(|_| restore) {
+            self.prefs.app_mode = app_mode.clone();
+            let _ = self
+                .header
+                .sender()
+                .send(HeaderInput::ChangeToKnownAppMode(app_mode));
+        }
+        self.filter_changed = true;
+        self.filter_error = None;
+        let _ = self
+            .header
+            .sender()
+            .send(HeaderInput::ShowBookmarkBtn(true));
+        let _ = self.header.sender().send(HeaderInput::SetBookmark(
+            folder.roots.iter().all(|root| self.prefs.is_favorite(root)),
+        ));
+        // the view page is shown as soon as the scan found something to pick (unless it's a rescan)
+        if self.curr_folder.as_ref().map(|f| &f.root_path) != Some(&folder.root_path) {
+            self.current_page = AppPages::ChooseFolder;
+            // the seed is used once, reopening the folder later must not throw its progress away
+            if let (Ok(Some(seed)), false) = (self.seed, restore) {
+                folder.set_seed(seed);
+                self.seed = Ok(None);
+                self.seed_entry.set_text("");
+            }
+        }
+
+        // scan in the background, the items are added to the folder as they are found
+        self.scan_id += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.scan = Some((cancel.clone(), ScanProgress::default()));
+        let (scan_id, roots, options) = (self.scan_id, folder.roots.clone(), folder.scan_options());
+        self.curr_folder = Some(folder);
+        if self.prefs.last_roots != roots {
+            self.prefs.last_roots = roots.clone();
+            if let Err(err) = self.prefs.save() {
+                push_error!(err, sender);
+            }
+        }
+        sender.spawn_command(move |out| {
+            let result = scanner::scan_roots(
+                &roots,
+                &options,
+                &cancel,
+                |items, progress| {
+                    let _ = out.send(AppCommand::ScanUpdate(scan_id, items, progress));
+                },
+                |err| {
+                    let _ = out.send(AppCommand::ScanError(scan_id, err));
+                },
+            );
+            let _ = out.send(AppCommand::ScanDone(scan_id, result));
+        });
+    }
+
+    /// write the items seen in the open folder and remember where the user stopped, a folder still being
+    /// scanned only has a part of its deck so its session is skipped
+    fn save_session(&mut self) {
+        let app_mode = self.prefs.app_mode.clone();
+        if let Some(folder) = self.curr_folder.as_mut() {
+            let _ = folder.flush_deck();
+        }
+        if self.scan.is_some() {
+            return;
+        }
+        if let Some(folder) = self.curr_folder.as_mut() {
+            if !folder.is_empty(app_mode.clone()) {
+                folder.save_session(app_mode);
+            }
+        }
+    }
+
+    /// write the seen items once the user stops picking for a moment, not at each pick
+    fn schedule_deck_flush(&mut self, sender: &ComponentSender<Self>) {
+        self.deck_flush_steps += 1;
+        let (sender, steps) = (sender.clone(), self.deck_flush_steps);
+        glib::timeout_add_local_once(Duration::from_secs(2), move || {
+            sender.input(AppInput::FlushDeck(steps))
+        });
+    }
+
+    /// watch the open roots to keep their items up to date
+    fn watch_folder(&mut self, sender: &ComponentSender<Self>) {
+        if let Some(folder) = self.curr_folder.as_ref() {
+            self.watchers = folder
+                .roots
+                .iter()
+                .map(|root| {
+                    let (sender, event_root) = (sender.clone(), root.clone());
+                    let mut watcher = FolderWatcher::new(root, move |event| {
+                        sender.input(AppInput::FolderChanged(event_root.clone(), event))
+                    });
+                    watcher.watch(folder.watched_dirs(root));
+                    watcher
+                })
+                .collect();
+        }
+    }
+
+    fn finish_scan(
+        &mut self,
+        result: Result<Option<AppMode>, AppError>,
+        sender: &ComponentSender<Self>,
+    ) {
+        let folder = match self.curr_folder.as_mut() {
+            Some(f) => f,
+            None => return,
+        };
+        let app_mode = self.prefs.app_mode.clone();
+        let has_items = [AppMode::SubFolders, AppMode::Images, AppMode::Videos]
+            .into_iter()
+            .any(|m| folder.has_items(m));
+        let restoring = std::mem::take(&mut self.restoring);
+
+        match result {
+            Ok(rec_app_mode) => {
+                folder.finish_scan();
+                if restoring && !folder.restore_session(app_mode.clone()) {
+                    push_toast!(
+                        "Nothing to continue in this folder, starting over",
+                        3,
+                        sender
+                    );
+                }
+                // the current mode has nothing to pick, switch to the mode recommended by the scan
+                if !folder.has_items(app_mode) && has_items {
+                    let new_app_mode = rec_app_mode.unwrap_or_default();
+                    folder.reset_curr_index(new_app_mode.clone());
+                    self.prefs.app_mode = new_app_mode.clone();
+                    let _ = self
+                        .header
+                        .sender()
+                        .send(HeaderInput::ChangeToKnownAppMode(new_app_mode));
+                    self.current_page = AppPages::ViewFolder;
+                }
+                if has_items {
+                    return self.watch_folder(sender);
+                }
+                push_toast!(
+                    "No subfolder, image or video found in this folder",
+                    3,
+                    sender
+                );
+            }
+            Err(AppError::ScanCancelled) if folder.has_items(app_mode) => {
+                push_toast!(
+                    "Scan cancelled, only a part of the folder is shown",
+                    3,
+                    sender
+                );
+                return self.watch_folder(sender);
+            }
+            Err(err) => push_error!(err, sender),
+        }
+
+        // nothing to show
+        let _ = self
+            .header
+            .sender()
+            .send(HeaderInput::ShowBookmarkBtn(false));
+        self.current_page = AppPages::ChooseFolder;
+        self.close_folder();
+    }
+
+    /// list the items of the folder in the gallery (only when it's shown, the thumbnails of the cells are
+    /// loaded as they're scrolled into view) and scroll to the current item
+    fn fill_gallery(&mut self) {
+        self.gallery.clear();
+        let folder = match (&self.curr_folder, &self.current_page) {
+            (Some(f), AppPages::Gallery) => f,
+            _ => return,
+        };
+        let entries = folder.gallery(self.prefs.app_mode.clone());
+        let has_entries = !entries.is_empty();
+        self.gallery
+            .extend_from_iter(entries.into_iter().map(GalleryItem::new));
+        if has_entries {
+            self.gallery
+                .view
+                .scroll_to(folder.curr as u32, gtk::ListScrollFlags::FOCUS, None);
+        }
+    }
+
+    /// flip through the images of the current subfolder, starting at its cover
+    fn open_browser(&mut self, sender: &ComponentSender<Self>) {
+        let subfolder = match (&self.curr_folder, &self.prefs.app_mode) {
+            (Some(f), AppMode::SubFolders) => match f.subfolders.get(f.curr) {
+                Some(sf) => sf,
+                None => return,
+            },
+            (Some(f), AppMode::All) => match f.all.get(f.curr) {
+                Some(AnyItem::SubFolder(sf)) => sf,
+                Some(_) => return push_toast!("Only the subfolders can be browsed", 2, sender),
+                None => return,
+            },
+            _ => return push_toast!("Only the subfolders can be browsed", 2, sender),
+        };
+        let browser = match SubFolderBrowser::new(subfolder) {
+            Ok(b) if b.count() > 0 => b,
+            Ok(_) => return push_toast!("This subfolder has no image", 2, sender),
+            Err(err) => return push_error!(err, sender),
+        };
+
+        self.filmstrip.clear();
+        self.filmstrip
+            .extend_from_iter(browser.entries().into_iter().map(GalleryItem::new));
+        let curr = browser.curr;
+        self.browser = Some(browser);
+        self.current_page = AppPages::Browse;
+        // the image of the previous subfolder mustn't show while the first one loads
+        self.browser_image = None;
+        self.browser_changed = true;
+        self.load_browser_image(sender);
+        self.filmstrip
+            .view
+            .scroll_to(curr as u32, gtk::ListScrollFlags::SELECT, None);
+        self.browse_page.grab_focus();
+    }
+
+    /// change the image shown by the subfolder browser
+    fn browse(
+        &mut self,
+        change: impl FnOnce(&mut SubFolderBrowser),
+        sender: &ComponentSender<Self>,
+    ) {
+        let browser = match self.browser.as_mut() {
+            Some(b) => b,
+            None => return,
+        };
+        let prev = browser.curr;
+        change(browser);
+        if browser.curr != prev {
+            self.filmstrip
+                .view
+                .scroll_to(browser.curr as u32, gtk::ListScrollFlags::SELECT, None);
+            self.load_browser_image(sender);
+        }
+    }
+
+    /// decode the shown image of the browser in the background, the previous one stays until it's ready
+    fn load_browser_image(&self, sender: &ComponentSender<Self>) {
+        let image = match self.browser.as_ref().and_then(|b| b.curr_image()) {
+            Some(image) => image,
+            None => return,
+        };
+        sender.spawn_command(move |out| {
+            let path = backend::get_image_thumbnail(&image, BROWSER_IMAGE_SIZE as u32);
+            let size = BROWSER_IMAGE_SIZE;
+            let texture = Pixbuf::from_file_at_scale(path, size, size, true)
+                .ok()
+                .map(|pixbuf| gdk::Texture::for_pixbuf(&pixbuf));
+            let _ = out.send(AppCommand::BrowserImage(image, texture));
+        });
+    }
+
+    /// name of the browsed subfolder and position of the shown image
+    fn browser_position(&self) -> String {
+        match &self.browser {
+            Some(b) => format!("{} · {} / {}", b.name, b.curr + 1, b.count()),
+            None => String::new(),
+        }
+    }
+
+    /// resolve the thumbnail of the current item when it changes, the videos ones are extracted in
+    /// the background
+    fn refresh_thumbnail(&mut self, sender: &ComponentSender<Self>) {
+        let cover = |sf: &backend::SubFolder| {
+            sf.thumbnail
+                .as_ref()
+                .map(|t| format!("{}/{t}", sf.get_path()))
+        };
+        let source = self
+            .curr_folder
+            .as_ref()
+            .and_then(|f| match self.prefs.app_mode {
+                AppMode::SubFolders => f.subfolders.get(f.curr).and_then(cover),
+                AppMode::Images => f.images.get(f.curr).map(|i| i.filepath.clone()),
+                AppMode::Videos => f.videos.get(f.curr).map(|v| v.filepath.clone()),
+                AppMode::All => f.all.get(f.curr).and_then(|i| match i {
+                    AnyItem::SubFolder(sf) => cover(sf),
+                    AnyItem::Media(m) => Some(m.filepath.clone()),
+                }),
+            });
+        if source == self.thumbnail.0 {
+            return;
+        }
+
+        // in the mixed mode the thumbnail is resolved according to the kind of the item, in the background since
+        // the shared cache is read from the disk (and the one of a video may have to be generated)
+        let thumbnail = source.as_ref().map(|filepath| {
+            let (filepath, kind) = (filepath.clone(), self.curr_kind());
+            sender.spawn_command(move |out| {
+                let result = match kind {
+                    AppMode::SubFolders | AppMode::All => {
+                        Ok(backend::get_image_thumbnail(&filepath, 512))
+                    }
+                    AppMode::Images => Ok(backend::get_image_thumbnail(&filepath, 1024)),
+                    AppMode::Videos => backend::get_video_thumbnail(&filepath),
+                };
+                let _ = out.send(AppCommand::ItemThumbnail(filepath, result));
+            });
+            Thumbnail::Loading
+        });
+        self.thumbnail = (source, thumbnail);
+    }
+
+    /// load the current video in the player when the item changes, the still is shown until it's played
+    fn refresh_player(&mut self, sender: &ComponentSender<Self>) {
+        if !self.item_changed {
+            return;
+        }
+        let muted = self.player.as_ref().is_some_and(|p| p.is_muted());
+        let video = match (&self.curr_folder, &self.prefs.app_mode) {
+            (Some(f), AppMode::Videos) => f.videos.get(f.curr).map(|v| v.filepath.clone()),
+            (Some(f), AppMode::All) => match f.all.get(f.curr) {
+                Some(AnyItem::Media(m)) if m.media_type == MediaType::Video => {
+                    Some(m.filepath.clone())
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        self.player = video.map(|filepath| {
+            let player = VideoPlayer::new(&filepath, muted);
+            let (playing_sender, muted_sender) = (sender.clone(), sender.clone());
+            player
+                .media
+                .connect_playing_notify(move |_| playing_sender.input(AppInput::PlayerChanged));
+            player
+                .media
+                .connect_muted_notify(move |_| muted_sender.input(AppInput::PlayerChanged));
+            let error_sender = sender.clone();
+            let path = filepath.clone();
+            player.media.connect_error_notify(move |media| {
+                if let Some(err) = media.error() {
+                    error_sender.input(AppInput::PlayerFailed(path.clone(), err.to_string()))
+                }
+            });
+            // no media backend at all
+            if let Some(err) = player.media.error() {
+                sender.input(AppInput::PlayerFailed(filepath, err.to_string()));
+            }
+            player
+        });
+    }
+
+    /// edit the rating, tags or note of the current item
+    fn edit_curr_meta(
+        &mut self,
+        sender: &ComponentSender<Self>,
+        edit: impl FnOnce(&mut library::ItemMeta),
+    ) {
+        if let Some(folder) = self.curr_folder.as_mut() {
+            if let Err(err) = folder.edit_curr_meta(self.prefs.app_mode.clone(), edit) {
+                push_error!(err, sender);
+            }
+        }
+    }
+
+    /// show the metadata of the current item when it changes
+    fn refresh_item_meta(&mut self) {
+        let app_mode = self.prefs.app_mode.clone();
+        let folder = self.curr_folder.as_ref();
+        let item = folder.and_then(|f| {
+            let key = f.curr_key(app_mode.clone())?;
+            Some((app_mode.clone(), format!("{}/{key}", f.root_path)))
+        });
+        let rating = folder
+            .and_then(|f| f.curr_meta(app_mode))
+            .and_then(|m| m.rating);
+        if item != self.shown_item || rating != self.shown_rating {
+            self.shown_rating = rating;
+            let _ = self.header.sender().send(HeaderInput::SetRating(rating));
+        }
+
+        if item != self.shown_item {
+            self.shown_item = item;
+            self.item_changed = true;
+            self.publish_pick();
+        }
+    }
+
+    /// tell the programs listening on the session bus about the current item
+    fn publish_pick(&self) {
+        let dbus = match &self.dbus {
+            Some(d) => d,
+            None => return,
+        };
+        let app_mode = self.prefs.app_mode.clone();
+        let item = self
+            .curr_folder
+            .as_ref()
+            .and_then(|f| f.curr_item(app_mode.clone()));
+        let (path, name) = match item {
+            Some((path, name)) => (path, name.to_string()),
+            None => (String::new(), String::new()),
+        };
+        dbus.set_current(CurrentPick {
+            path,
+            name,
+            mode: app_mode.name().to_string(),
+        });
+    }
+
+    /// tags and note of the current item, separated by commas for the tags
+    fn curr_meta_text(&self) -> (String, String) {
+        let meta = self
+            .curr_folder
+            .as_ref()
+            .and_then(|f| f.curr_meta(self.prefs.app_mode.clone()));
+        match meta {
+            Some(m) => (m.tags.join(", "), m.note.clone()),
+            None => (String::new(), String::new()),
+        }
+    }
+
+    /// number of items left to pick with the filter of the folder
+    fn filter_count(&self) -> String {
+        let (unseen, matching, total) = match &self.curr_folder {
+            Some(f) => f.unseen_count(self.prefs.app_mode.clone()),
+            None => return String::new(),
+        };
+        match matching == total {
+            true => format!("{total} items, {unseen} not seen yet"),
+            false => format!("{matching} of {total} items match, {unseen} not seen yet"),
+        }
+    }
+
+    /// nothing could render the current video
+    fn shows_video_fallback(&self) -> bool {
+        self.curr_kind() == AppMode::Videos && self.thumbnail.1 == Some(Thumbnail::Fallback)
+    }
+
+    /// kind of the current item, which varies from one item to the other in the mixed mode
+    fn curr_kind(&self) -> AppMode {
+        match &self.curr_folder {
+            Some(f) => f.curr_kind(self.prefs.app_mode.clone()),
+            None => self.prefs.app_mode.clone(),
+        }
+    }
+
+    /// root the current item comes from, only worth showing in a multi-root session
+    fn curr_origin(&self) -> Option<String> {
+        let folder = self.curr_folder.as_ref().filter(|f| f.is_multi_root())?;
+        let root = folder.curr_root(self.prefs.app_mode.clone())?;
+        Some(format!("From {root}"))
+    }
+
+    /// the scan depth of the open folder, or the one about to be applied
+    fn shown_scan_depth(&self) -> usize {
+        let folder = match &self.curr_folder {
+            Some(f) => f,
+            None => return 1,
+        };
+        match &self.pending_scan_depth {
+            Some((root_path, max_depth)) if *root_path == folder.root_path => *max_depth,
+            _ => folder.scan_options().max_depth,
+        }
+    }
+
+    /// explain why the current item came up
+    fn pick_details(&self) -> String {
+        let folder = match &self.curr_folder {
+            Some(f) => f,
+            None => return String::new(),
+        };
+        let app_mode = self.prefs.app_mode.clone();
+        let curr_pick = folder.last_pick.as_ref().filter(|p| {
+            folder
+                .curr_key(app_mode.clone())
+                .is_some_and(|k| k == p.key)
+        });
+        let details = match (folder.pick_mode(), curr_pick) {
+            (PickMode::Weighted, Some(pick)) => format!(
+                "Drawn among {} items with a {:.1}% chance\nLast shown: {}\nTime factor: ×{:.2}   Size factor: ×{:.2}   Rating factor: ×{:.2}   Weight: {:.2}",
+                pick.candidates,
+                pick.probability * 100.0,
+                weighting::format_age(pick.weight.since_last_shown),
+                pick.weight.time_factor,
+                pick.weight.count_factor,
+                pick.weight.rating_factor,
+                pick.weight.value()
+            ),
+            (PickMode::Weighted, None) => {
+                "The next item will be drawn according to its weight".to_string()
+            }
+            (PickMode::Shuffle, _) => {
+                let (unseen, matching, _) = folder.unseen_count(app_mode);
+                format!("Every item has the same chance, {unseen} out of {matching} not seen yet in this round")
+            }
+        };
+        match self.curr_origin() {
+            Some(origin) => format!("{origin}\n{details}"),
+            None => details,
+        }
     }
 }
@@ -0,0 +1,122 @@
+use relm4::gtk::{self, prelude::*};
+
+/// Playback of the current video inside of the view card
+#[derive(Debug)]
+pub struct VideoPlayer {
+    pub filepath: String,
+    pub media: gtk::MediaFile,
+    /// the user started the playback, the player replaces the still until the item changes
+    started: bool,
+    /// muted playback while the pointer is over the still
+    preview: bool,
+    /// sound setting of the user, a preview is always muted
+    muted: bool,
+    /// the media backend can't decode the video, the still stays shown
+    failed: bool,
+}
+
+impl VideoPlayer {
+    pub fn new(filepath: &str, muted: bool) -> Self {
+        let media = gtk::MediaFile::for_filename(filepath);
+        media.set_muted(muted);
+        Self {
+            filepath: filepath.to_string(),
+            media,
+            started: false,
+            preview: false,
+            muted,
+            failed: false,
+        }
+    }
+
+    /// whether the player is shown instead of the still
+    pub fn is_shown(&self) -> bool {
+        !self.failed && (self.started || self.preview)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.preview && self.media.is_playing()
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.failed
+    }
+
+    pub fn toggle_play(&mut self) {
+        if self.failed {
+            return;
+        }
+        // the preview goes on, with the sound this time
+        if self.preview {
+            self.preview = false;
+            self.media.set_muted(self.muted);
+            self.started = true;
+            return self.media.play();
+        }
+        self.started = true;
+        self.media.set_playing(!self.media.is_playing());
+    }
+
+    pub fn toggle_muted(&mut self) {
+        self.muted = !self.muted;
+        if !self.preview {
+            self.media.set_muted(self.muted);
+        }
+    }
+
+    /// play a muted preview while the pointer is over the still, unless the user already started the video
+    pub fn set_preview(&mut self, hovered: bool) {
+        if self.failed || self.started || self.preview == hovered {
+            return;
+        }
+        self.preview = hovered;
+        match hovered {
+            true => {
+                self.media.set_muted(true);
+                self.media.play();
+            }
+            false => {
+                self.media.pause();
+                self.media.seek(0);
+                self.media.set_muted(self.muted);
+            }
+        }
+    }
+
+    /// Follow the changes made with the controls of the video. During a preview the video plays muted, any
+    /// other state means that the user took over, the playback goes on as if it was started from the app
+    pub fn sync(&mut self) {
+        if self.failed {
+            return;
+        }
+        let (playing, muted) = (self.media.is_playing(), self.media.is_muted());
+        if self.preview {
+            if playing && muted {
+                return;
+            }
+            self.preview = false;
+            self.started = true;
+            // unmuted by the user, or paused and heard once resumed
+            self.muted &= muted;
+            return self.media.set_muted(self.muted);
+        }
+        self.started |= playing;
+        self.muted = muted;
+    }
+
+    pub fn fail(&mut self) {
+        self.failed = true;
+        self.media.pause();
+    }
+}
+
+impl Drop for VideoPlayer {
+    fn drop(&mut self) {
+        // the gtk::Video may keep the stream alive a bit longer, it must not be heard anymore
+        self.media.pause();
+    }
+}
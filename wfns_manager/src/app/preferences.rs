@@ -1,72 +1,462 @@
 use std::{
-    collections::HashSet,
-    fs::{self, File},
+    fs, io,
     path::{Path, PathBuf},
 };
 
-#[derive(Debug, Clone, PartialEq, Default)]
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+
+use crate::app::error::AppError;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AppMode {
     #[default]
     SubFolders,
     Images,
     Videos,
+    /// the subfolders, images and videos shuffled together
+    All,
+}
+
+impl AppMode {
+    /// name of the mode on the command line and over D-Bus
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppMode::SubFolders => "subfolders",
+            AppMode::Images => "images",
+            AppMode::Videos => "videos",
+            AppMode::All => "all",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "subfolders" => Some(AppMode::SubFolders),
+            "images" => Some(AppMode::Images),
+            "videos" => Some(AppMode::Videos),
+            "all" => Some(AppMode::All),
+            _ => None,
+        }
+    }
+}
+
+/// size of the main window when it isn't maximized
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowState {
+    pub width: i32,
+    pub height: i32,
+    pub maximized: bool,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 800,
+            maximized: true,
+        }
+    }
+}
+
+/// a bookmarked folder, in the order chosen by the user
+#[derive(Debug, Clone, PartialEq)]
+pub struct Favorite {
+    pub path: String,
+    /// name shown instead of the folder's name
+    pub alias: Option<String>,
+    /// mode the folder is opened in, the last used one if `None`
+    pub app_mode: Option<AppMode>,
+}
+
+impl Favorite {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            alias: None,
+            app_mode: None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match &self.alias {
+            Some(alias) => alias.clone(),
+            None => Path::new(&self.path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or(self.path.clone()),
+        }
+    }
+
+    /// the folder was deleted or is on an unmounted drive
+    pub fn is_missing(&self) -> bool {
+        !Path::new(&self.path).is_dir()
+    }
+}
+
+/// version of the settings file written by this build, the keys it doesn't know are ignored
+const SETTINGS_VERSION: i64 = 1;
+const SETTINGS_FILE: &str = "settings.toml";
+/// the favorites were the only thing saved before the settings file
+const LEGACY_FAVS_FILE: &str = "favs_folders";
+
+/// Content of the settings file. The values are optional so that a missing or invalid one falls back to its
+/// default instead of failing the whole file, the keys of newer versions are ignored
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SettingsFile {
+    #[serde(default, deserialize_with = "valid_or_none")]
+    version: Option<i64>,
+    #[serde(default, deserialize_with = "valid_or_none")]
+    app_mode: Option<AppMode>,
+    #[serde(default, deserialize_with = "valid_or_none")]
+    last_roots: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "valid_or_none")]
+    restore_session: Option<bool>,
+    #[serde(default, deserialize_with = "valid_or_none")]
+    window: Option<WindowFile>,
+    #[serde(default, deserialize_with = "valid_items")]
+    favorites: Vec<FavoriteFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WindowFile {
+    #[serde(default, deserialize_with = "valid_or_none")]
+    width: Option<i32>,
+    #[serde(default, deserialize_with = "valid_or_none")]
+    height: Option<i32>,
+    #[serde(default, deserialize_with = "valid_or_none")]
+    maximized: Option<bool>,
+}
+
+/// `[[favorites]]` tables, in the order of the list
+#[derive(Debug, Serialize, Deserialize)]
+struct FavoriteFile {
+    path: String,
+    #[serde(default, deserialize_with = "valid_or_none")]
+    alias: Option<String>,
+    #[serde(default, deserialize_with = "valid_or_none")]
+    app_mode: Option<AppMode>,
 }
 
-#[derive(Debug, Default)]
+/// `None` for a value of the wrong type
+fn valid_or_none<'de, D: Deserializer<'de>, T: DeserializeOwned>(
+    d: D,
+) -> Result<Option<T>, D::Error> {
+    Ok(toml::Value::deserialize(d)?.try_into().ok())
+}
+
+/// the items of an array that have the right type, the others are skipped
+fn valid_items<'de, D: Deserializer<'de>, T: DeserializeOwned>(d: D) -> Result<Vec<T>, D::Error> {
+    Ok(match toml::Value::deserialize(d)? {
+        toml::Value::Array(items) => items
+            .into_iter()
+            .filter_map(|i| i.try_into().ok())
+            .collect(),
+        _ => vec![],
+    })
+}
+
+#[derive(Debug)]
 pub struct AppPreferences {
-    pub favs_folders: HashSet<String>,
+    pub favs_folders: Vec<Favorite>,
     pub app_mode: AppMode,
+    /// roots of the last opened folder
+    pub last_roots: Vec<String>,
+    /// reopen the last folder where it was left when the app starts
+    pub restore_session: bool,
+    pub window: WindowState,
+    /// version of the loaded settings file
+    version: i64,
+    /// nothing is saved, the settings file was written by a newer version of the app and couldn't be backed up
+    /// (or couldn't be read at all)
+    read_only: bool,
 }
 
-/// Simple macro to return default when error (can be seen as an enhance '?')
-macro_rules! tod {
-    ($expr:expr) => {
-        match $expr {
-            Ok(val) => val,
-            Err(_) => return Self::default(),
+impl Default for AppPreferences {
+    fn default() -> Self {
+        Self {
+            favs_folders: vec![],
+            app_mode: AppMode::default(),
+            last_roots: vec![],
+            restore_session: false,
+            window: WindowState::default(),
+            version: SETTINGS_VERSION,
+            read_only: false,
         }
-    };
+    }
 }
 
 impl AppPreferences {
-    /// return the path to the app's config file (and ensure that all the necessary directories and files exists)
-    fn get_config_file_path() -> Result<PathBuf, ()> {
-        let mut config_path = dirs::config_dir().ok_or(())?;
+    /// return the app's config directory (and ensure that it exists)
+    fn get_config_dir() -> Result<PathBuf, AppError> {
+        let mut config_path = dirs::config_dir().ok_or(AppError::NoUserDirectory("config"))?;
 
         config_path.push("wfns_manager");
-        fs::create_dir_all(&config_path).map_err(|_| ())?;
+        fs::create_dir_all(&config_path).map_err(|err| AppError::io(config_path.display(), err))?;
+        Ok(config_path)
+    }
+
+    /// Load the settings file, it's created from the legacy favorites file the first time. A corrupted file is
+    /// moved aside (so that the next save doesn't erase it) and reported, a file written by a newer version of
+    /// the app is backed up before this one saves its settings over it
+    pub fn load() -> Result<Self, AppError> {
+        let config_dir = Self::get_config_dir()?;
+        let settings_path = config_dir.join(SETTINGS_FILE);
+        let datas = match fs::read_to_string(&settings_path) {
+            Ok(datas) => datas,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::migrate(&config_dir),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                return Err(Self::set_aside(&settings_path, err.to_string()))
+            }
+            Err(err) => return Err(AppError::io(settings_path.display(), err)),
+        };
+        let file = toml::from_str(&datas)
+            .map_err(|err| Self::set_aside(&settings_path, err.message().to_string()))?;
 
-        config_path.push("favs_folders");
-        if !Path::exists(&config_path) {
-            File::create(&config_path).map_err(|_| ())?;
+        let mut prefs = Self::from_file(file);
+        if prefs.version > SETTINGS_VERSION {
+            let backup_path = settings_path.with_extension(format!("toml.v{}", prefs.version));
+            // its settings would be lost otherwise, so nothing is saved when it can't be backed up
+            prefs.read_only =
+                !backup_path.exists() && fs::copy(&settings_path, &backup_path).is_err();
         }
+        Ok(prefs)
+    }
 
-        Ok(config_path)
+    /// Default settings used when the settings file couldn't be loaded. Unless the file was set aside, they
+    /// aren't saved: the user's settings are still in it and would be overwritten
+    pub fn fallback(err: &AppError) -> Self {
+        Self {
+            read_only: !matches!(
+                err,
+                AppError::ConfigCorrupt {
+                    backup: Some(_),
+                    ..
+                }
+            ),
+            ..Default::default()
+        }
     }
 
-    pub fn load() -> Self {
-        let config_file_path = tod!(Self::get_config_file_path());
-        let datas = tod!(fs::read_to_string(config_file_path));
-        let favs_folders = datas.lines().map(|s| s.to_string()).collect::<Vec<_>>();
-        match favs_folders.is_empty() {
-            true => Self::default(),
-            false => Self {
-                favs_folders: HashSet::from_iter(favs_folders),
-                ..Default::default()
-            },
+    /// the settings of a parsed settings file, the invalid values are replaced by the default ones
+    fn from_file(file: SettingsFile) -> Self {
+        let mut prefs = Self::default();
+        prefs.version = file.version.unwrap_or(prefs.version);
+        prefs.app_mode = file.app_mode.unwrap_or_default();
+        prefs.last_roots = file.last_roots.unwrap_or_default();
+        prefs.restore_session = file.restore_session.unwrap_or(prefs.restore_session);
+        if let Some(window) = file.window {
+            let window_state = &mut prefs.window;
+            let size = |s: Option<i32>| s.filter(|s| *s > 0);
+            window_state.width = size(window.width).unwrap_or(window_state.width);
+            window_state.height = size(window.height).unwrap_or(window_state.height);
+            window_state.maximized = window.maximized.unwrap_or(window_state.maximized);
+        }
+        for fav in file.favorites {
+            if !prefs.is_favorite(&fav.path) {
+                prefs.favs_folders.push(Favorite {
+                    path: fav.path,
+                    alias: fav.alias,
+                    app_mode: fav.app_mode,
+                });
+            }
+        }
+        prefs
+    }
+
+    /// the favorites were sorted by path before they could be reordered
+    fn legacy_favorites(mut paths: Vec<String>) -> Vec<Favorite> {
+        paths.retain(|p| !p.is_empty());
+        paths.sort();
+        paths.dedup();
+        paths.into_iter().map(Favorite::new).collect()
+    }
+
+    /// import the legacy favorites file (one path per line) into the settings file, it's kept as a backup
+    fn migrate(config_dir: &Path) -> Result<Self, AppError> {
+        let legacy_path = config_dir.join(LEGACY_FAVS_FILE);
+        let datas = match fs::read_to_string(&legacy_path) {
+            Ok(datas) => datas,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(AppError::io(legacy_path.display(), err)),
+        };
+        let prefs = Self {
+            favs_folders: Self::legacy_favorites(datas.lines().map(|s| s.to_string()).collect()),
+            ..Default::default()
+        };
+        prefs.save()?;
+        let _ = fs::rename(&legacy_path, legacy_path.with_extension("bak"));
+        Ok(prefs)
+    }
+
+    /// move a corrupted settings file next to the new one
+    fn set_aside(settings_path: &Path, reason: String) -> AppError {
+        let backup_path = settings_path.with_extension("toml.corrupt");
+        AppError::ConfigCorrupt {
+            path: settings_path.display().to_string(),
+            reason,
+            backup: fs::rename(settings_path, &backup_path)
+                .ok()
+                .map(|_| backup_path.display().to_string()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        if self.read_only {
+            return Ok(());
+        }
+        let settings_path = Self::get_config_dir()?.join(SETTINGS_FILE);
+        // written next to it first, a crash while writing can't leave a truncated file
+        let tmp_path = settings_path.with_extension("toml.tmp");
+        toml::to_string(&self.to_file())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            .and_then(|datas| fs::write(&tmp_path, datas))
+            .and_then(|_| fs::rename(&tmp_path, &settings_path))
+            .map_err(|err| AppError::io(settings_path.display(), err))
+    }
+
+    fn to_file(&self) -> SettingsFile {
+        SettingsFile {
+            version: Some(SETTINGS_VERSION),
+            app_mode: Some(self.app_mode.clone()),
+            last_roots: Some(self.last_roots.clone()),
+            restore_session: Some(self.restore_session),
+            window: Some(WindowFile {
+                width: Some(self.window.width),
+                height: Some(self.window.height),
+                maximized: Some(self.window.maximized),
+            }),
+            favorites: self
+                .favs_folders
+                .iter()
+                .map(|fav| FavoriteFile {
+                    path: fav.path.clone(),
+                    alias: fav.alias.clone(),
+                    app_mode: fav.app_mode.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn favorite(&self, path: &str) -> Option<&Favorite> {
+        self.favs_folders.iter().find(|f| f.path == path)
+    }
+
+    pub fn favorite_mut(&mut self, path: &str) -> Option<&mut Favorite> {
+        self.favs_folders.iter_mut().find(|f| f.path == path)
+    }
+
+    pub fn is_favorite(&self, path: &str) -> bool {
+        self.favorite(path).is_some()
+    }
+
+    /// append a favorite, `false` if it already was one
+    pub fn add_favorite(&mut self, path: &str) -> bool {
+        if self.is_favorite(path) {
+            return false;
         }
+        self.favs_folders.push(Favorite::new(path.to_string()));
+        true
+    }
+
+    /// `false` if it wasn't a favorite
+    pub fn remove_favorite(&mut self, path: &str) -> bool {
+        let len = self.favs_folders.len();
+        self.favs_folders.retain(|f| f.path != path);
+        self.favs_folders.len() != len
     }
 
-    pub fn save(&self) -> Result<(), ()> {
-        let config_file_path = Self::get_config_file_path()?;
-        fs::write(
-            config_file_path,
+    /// move a favorite to the position `to` in the list
+    pub fn move_favorite(&mut self, path: &str, to: usize) {
+        if let Some(from) = self.favs_folders.iter().position(|f| f.path == path) {
+            let fav = self.favs_folders.remove(from);
             self.favs_folders
-                .clone()
-                .into_iter()
-                .collect::<Vec<_>>()
-                .join("\n"),
-        )
-        .map_err(|_| ())
+                .insert(to.min(self.favs_folders.len()), fav);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(datas: &str) -> AppPreferences {
+        AppPreferences::from_file(toml::from_str(datas).unwrap())
+    }
+
+    #[test]
+    fn writes_and_loads_back_the_settings() {
+        let mut prefs = AppPreferences {
+            app_mode: AppMode::Videos,
+            last_roots: vec!["/a".to_string(), "/b".to_string()],
+            restore_session: true,
+            window: WindowState {
+                width: 800,
+                height: 600,
+                maximized: false,
+            },
+            ..Default::default()
+        };
+        prefs.add_favorite("/z");
+        prefs.add_favorite("/a \"quoted\"");
+        if let Some(fav) = prefs.favorite_mut("/z") {
+            fav.alias = Some("Zed".to_string());
+            fav.app_mode = Some(AppMode::All);
+        }
+
+        let loaded = load(&toml::to_string(&prefs.to_file()).unwrap());
+        assert_eq!(loaded.version, SETTINGS_VERSION);
+        assert_eq!(loaded.favs_folders, prefs.favs_folders);
+        assert_eq!(loaded.app_mode, prefs.app_mode);
+        assert_eq!(loaded.last_roots, prefs.last_roots);
+        assert_eq!(loaded.restore_session, prefs.restore_session);
+        assert_eq!(loaded.window, prefs.window);
+    }
+
+    #[test]
+    fn migrates_the_legacy_favorites_file() {
+        let lines = "/b\n\n/a\n/b\n".lines().map(|l| l.to_string()).collect();
+        assert_eq!(
+            AppPreferences::legacy_favorites(lines),
+            [
+                Favorite::new("/a".to_string()),
+                Favorite::new("/b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn replaces_the_invalid_values_by_the_default_ones() {
+        let prefs = load(
+            "app_mode = \"gallery\"\nrestore_session = 1\n[window]\nwidth = -5\nheight = 99999999999\n\
+            [[favorites]]\nalias = \"no path\"\n[[favorites]]\npath = \"/a\"\n[[favorites]]\npath = \"/a\"",
+        );
+        let default = AppPreferences::default();
+        assert_eq!(prefs.app_mode, default.app_mode);
+        assert_eq!(prefs.restore_session, default.restore_session);
+        assert_eq!(prefs.window, default.window);
+        assert_eq!(prefs.favs_folders, [Favorite::new("/a".to_string())]);
+    }
+
+    #[test]
+    fn keeps_the_version_of_a_newer_file() {
+        let prefs = load(
+            "version = 2\napp_mode = \"images\"\n[[favorites]]\npath = \"/a\"\n[gallery]\nsize = 4",
+        );
+        assert_eq!(prefs.version, 2);
+        assert_eq!(prefs.app_mode, AppMode::Images);
+        assert_eq!(prefs.favs_folders.len(), 1);
+    }
+
+    #[test]
+    fn only_saves_over_a_file_set_aside() {
+        let corrupt = |backup: Option<&str>| AppError::ConfigCorrupt {
+            path: "/c/settings.toml".to_string(),
+            reason: "invalid".to_string(),
+            backup: backup.map(String::from),
+        };
+        assert!(!AppPreferences::fallback(&corrupt(Some("/c/settings.toml.corrupt"))).read_only);
+        assert!(AppPreferences::fallback(&corrupt(None)).read_only);
+        let io_error = io::Error::new(io::ErrorKind::Interrupted, "interrupted");
+        assert!(AppPreferences::fallback(&AppError::io("/c/settings.toml", io_error)).read_only);
     }
 }
@@ -0,0 +1,275 @@
+use std::{
+    fs, mem,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use crate::app::{
+    backend::{Media, MediaType, SubFolder},
+    error::AppError,
+    preferences::AppMode,
+};
+
+/// minimal interval between two updates sent by a running scan
+const SCAN_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// how deep a root folder is scanned, remembered per root
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanOptions {
+    /// 1 means that only the direct children of the root are scanned
+    pub max_depth: usize,
+    /// only the deepest folders containing medias are subfolders candidates (e.g. the items of a
+    /// `Category/Series/Item` library)
+    pub leaf_folders: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 1,
+            leaf_folders: false,
+        }
+    }
+}
+
+/// counters of a running scan
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanProgress {
+    /// number of directory entries read so far
+    pub scanned: usize,
+    pub subfolders: usize,
+    pub images: usize,
+    pub videos: usize,
+}
+
+/// items found since the last scan update
+#[derive(Debug, Default)]
+pub struct ScannedItems {
+    pub sf: Vec<SubFolder>,
+    pub img: Vec<Media>,
+    pub vid: Vec<Media>,
+}
+
+impl ScannedItems {
+    pub fn is_empty(&self) -> bool {
+        self.sf.is_empty() && self.img.is_empty() && self.vid.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sf.len() + self.img.len() + self.vid.len()
+    }
+
+    pub fn extend(&mut self, other: ScannedItems) {
+        self.sf.extend(other.sf);
+        self.img.extend(other.img);
+        self.vid.extend(other.vid);
+    }
+}
+
+struct Scanner<'a, F: FnMut(ScannedItems, ScanProgress)> {
+    root_folder: &'a str,
+    options: &'a ScanOptions,
+    cancel: &'a AtomicBool,
+    on_update: F,
+
+    batch: ScannedItems,
+    progress: ScanProgress,
+    last_update: Instant,
+    sent_items: bool,
+}
+
+/// scan the root folders one after the other (meant to be run in a background thread), the found items are
+/// sent by batches to `on_update` along with the scan progress, `cancel` stops the scan as soon as possible.
+/// In a multi-root session a root that can't be scanned (e.g. an unplugged disk) is reported to `on_error`
+/// and skipped. Once done, it gives insight to the app on the right app mode to use depending on the
+/// folders content
+pub fn scan_roots(
+    roots: &[String],
+    options: &ScanOptions,
+    cancel: &AtomicBool,
+    mut on_update: impl FnMut(ScannedItems, ScanProgress),
+    mut on_error: impl FnMut(AppError),
+) -> Result<Option<AppMode>, AppError> {
+    let mut progress = ScanProgress::default();
+    for root_folder in roots {
+        let mut scanner = Scanner {
+            root_folder,
+            options,
+            cancel,
+            on_update: &mut on_update,
+
+            batch: ScannedItems::default(),
+            progress,
+            last_update: Instant::now(),
+            sent_items: false,
+        };
+        let result = scanner.scan_dir("", 1);
+        scanner.send_update(true);
+        progress = scanner.progress;
+        match result {
+            Ok(_) => {}
+            Err(AppError::ScanCancelled) => return Err(AppError::ScanCancelled),
+            Err(err) if roots.len() == 1 => return Err(err),
+            Err(err) => on_error(err),
+        }
+    }
+
+    let p = progress;
+    let recommanded_app_mode = match (p.subfolders == 0, p.images == 0, p.videos == 0) {
+        (true, false, _) => Some(AppMode::Images),
+        (true, true, false) => Some(AppMode::Videos),
+        _ => None,
+    };
+    Ok(recommanded_app_mode)
+}
+
+/// scan a single entry of the root (e.g. a file or a folder that just appeared), `rel_path` being relative to the root
+pub fn scan_path(root_folder: &str, rel_path: &str, options: &ScanOptions) -> ScannedItems {
+    let depth = rel_path.split('/').count();
+    let metadata = match fs::symlink_metadata(format!("{root_folder}/{rel_path}")) {
+        Ok(metadata) => metadata,
+        Err(_) => return ScannedItems::default(), // already gone
+    };
+    if depth > options.max_depth {
+        return ScannedItems::default();
+    }
+
+    let mut items = ScannedItems::default();
+    let cancel = AtomicBool::new(false);
+    let mut scanner = Scanner {
+        root_folder,
+        options,
+        cancel: &cancel,
+        on_update: |batch, _| items.extend(batch),
+
+        batch: ScannedItems::default(),
+        progress: ScanProgress::default(),
+        last_update: Instant::now(),
+        sent_items: false,
+    };
+    let filename = rel_path.rsplit('/').next().unwrap_or(rel_path);
+    let _ = scanner.scan_entry(rel_path, filename, &metadata, depth);
+    scanner.send_update(true);
+    items
+}
+
+/// the directories under an entry of the root (the entry included) whose content is scanned, they have to be
+/// watched for the items later put in them to be found
+pub fn dirs_to_watch(root_folder: &str, rel_path: &str, options: &ScanOptions) -> Vec<String> {
+    let depth = rel_path.split('/').count();
+    let dir_path = format!("{root_folder}/{rel_path}");
+    let is_dir = fs::symlink_metadata(&dir_path).is_ok_and(|m| m.is_dir());
+    if !is_dir || depth >= options.max_depth {
+        return vec![];
+    }
+
+    let mut dirs = vec![rel_path.to_string()];
+    if let Ok(entries) = fs::read_dir(&dir_path) {
+        for entry in entries.flatten() {
+            let rel_entry = format!("{rel_path}/{}", entry.file_name().to_string_lossy());
+            dirs.extend(dirs_to_watch(root_folder, &rel_entry, options));
+        }
+    }
+    dirs
+}
+
+impl<F: FnMut(ScannedItems, ScanProgress)> Scanner<'_, F> {
+    /// the first items are sent right away so that the app can show them without waiting for the whole scan
+    fn send_update(&mut self, force: bool) {
+        let first_items = !self.sent_items && !self.batch.is_empty();
+        if force || first_items || self.last_update.elapsed() >= SCAN_UPDATE_INTERVAL {
+            self.sent_items |= !self.batch.is_empty();
+            (self.on_update)(mem::take(&mut self.batch), self.progress);
+            self.last_update = Instant::now();
+        }
+    }
+
+    /// scan a directory of the root (`rel_path` being relative to the root), recursing until `options.max_depth`,
+    /// return whether it directly contains medias and whether one of its descendants does
+    fn scan_dir(&mut self, rel_path: &str, depth: usize) -> Result<(bool, bool), AppError> {
+        let dir_path = match rel_path.is_empty() {
+            true => self.root_folder.to_string(),
+            false => format!("{}/{rel_path}", self.root_folder),
+        };
+        let (mut direct_media, mut nested_media) = (false, false);
+
+        let mut entries = fs::read_dir(&dir_path).map_err(|err| AppError::io(dir_path, err))?;
+        while let Some(Ok(entry)) = entries.next() {
+            if self.cancel.load(Ordering::Relaxed) {
+                return Err(AppError::ScanCancelled);
+            }
+            self.progress.scanned += 1;
+
+            let filename = entry.file_name().to_string_lossy().to_string();
+            let rel_entry = match rel_path.is_empty() {
+                true => filename.clone(),
+                false => format!("{rel_path}/{filename}"),
+            };
+            // read here rather than when filtering, the app would stat every item on its main thread
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let (is_media, has_media) = self.scan_entry(&rel_entry, &filename, &metadata, depth)?;
+            direct_media |= is_media;
+            nested_media |= has_media;
+            self.send_update(false);
+        }
+        Ok((direct_media, nested_media))
+    }
+
+    /// scan an entry of a directory located at `depth`, return whether it's a media and whether it's a directory
+    /// containing medias
+    fn scan_entry(
+        &mut self,
+        rel_entry: &str,
+        filename: &str,
+        metadata: &fs::Metadata,
+        depth: usize,
+    ) -> Result<(bool, bool), AppError> {
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+        if !metadata.is_dir() {
+            let media_type = match MediaType::from_filename(filename) {
+                Some(mt) => mt,
+                None => return Ok((false, false)),
+            };
+            let mut media = Media::new(rel_entry, self.root_folder, media_type.clone());
+            media.mtime = mtime;
+            match media_type {
+                MediaType::Image => {
+                    self.progress.images += 1;
+                    self.batch.img.push(media)
+                }
+                MediaType::Video => {
+                    self.progress.videos += 1;
+                    self.batch.vid.push(media)
+                }
+            }
+            return Ok((true, false));
+        }
+
+        let (child_direct, child_nested) = match depth < self.options.max_depth {
+            true => match self.scan_dir(rel_entry, depth + 1) {
+                Err(AppError::ScanCancelled) => return Err(AppError::ScanCancelled),
+                r => r.unwrap_or_default(),
+            },
+            false => (false, false),
+        };
+        if self.options.leaf_folders && child_nested {
+            return Ok((false, true)); // a deeper folder is the candidate
+        }
+
+        let mut subfolder = SubFolder::new(rel_entry, self.root_folder);
+        subfolder.mtime = mtime;
+        let has_media = child_direct || subfolder.images_count > 0;
+        if !self.options.leaf_folders || has_media {
+            self.progress.subfolders += 1;
+            self.batch.sf.push(subfolder);
+        }
+        Ok((false, child_nested || has_media))
+    }
+}
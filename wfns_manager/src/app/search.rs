@@ -0,0 +1,29 @@
+/// an item of the open root matching the search
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub key: String,
+    pub name: String,
+    /// small image of the item, if there is one available right away
+    pub thumbnail: Option<String>,
+}
+
+/// score of `candidate` for a fuzzy `query` (its characters in the same order, but not necessarily next to
+/// each other), `None` if it doesn't match. Consecutive characters and the ones starting a word score more,
+/// shorter candidates come first
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate = candidate.to_lowercase().chars().collect::<Vec<_>>();
+    let (mut score, mut pos, mut prev_match) = (0, 0, None);
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = (pos..candidate.len()).find(|i| candidate[*i] == q)?;
+        score += 10;
+        if i > 0 && prev_match == Some(i - 1) {
+            score += 50;
+        }
+        if i == 0 || !candidate[i - 1].is_alphanumeric() {
+            score += 30;
+        }
+        prev_match = Some(i);
+        pos = i + 1;
+    }
+    Some(score - candidate.len() as i64)
+}
@@ -0,0 +1,281 @@
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use relm4::gtk::{
+    gdk_pixbuf::Pixbuf,
+    gio::{self, prelude::*},
+};
+
+use crate::app::error::AppError;
+
+/// directories of the freedesktop thumbnail cache and the maximum size of their thumbnails
+const SIZES: [(&str, u32); 4] = [
+    ("normal", 128),
+    ("large", 256),
+    ("x-large", 512),
+    ("xx-large", 1024),
+];
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// uri and modification date of a file, as stored in its thumbnails
+fn file_identity(filepath: &str) -> Option<(String, String)> {
+    let mtime = fs::metadata(filepath)
+        .and_then(|m| m.modified())
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    let uri = gio::File::for_path(filepath).uri().to_string();
+    Some((uri, mtime.to_string()))
+}
+
+fn get_thumbnails_dir() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("thumbnails"))
+}
+
+/// Look for a valid thumbnail of a file in the shared thumbnail cache of the freedesktop standard (the one
+/// filled by the file managers). The smallest one of at least `min_size` pixels is preferred, otherwise the
+/// largest smaller one, it's returned with the size of its directory
+pub fn lookup(filepath: &str, min_size: u32) -> Option<(PathBuf, u32)> {
+    let (uri, mtime) = file_identity(filepath)?;
+    let thumbnails_dir = get_thumbnails_dir()?;
+    let name = format!("{:x}.png", md5::compute(&uri));
+
+    let (smaller, larger): (Vec<_>, Vec<_>) = SIZES.iter().partition(|(_, size)| *size < min_size);
+    larger
+        .into_iter()
+        .chain(smaller.into_iter().rev())
+        .map(|&(dir, size)| (thumbnails_dir.join(dir).join(&name), size))
+        .find(|(path, _)| {
+            // a thumbnail is only valid for the file it was made from, in its current version
+            let texts = fs::read(path).ok().map(|png| text_chunks(&png));
+            texts.is_some_and(|t| {
+                t.get("Thumb::URI") == Some(&uri) && t.get("Thumb::MTime") == Some(&mtime)
+            })
+        })
+}
+
+/// Share a PNG thumbnail generated by the app in the freedesktop thumbnail cache so that the other apps
+/// don't have to generate it again. It is put in the largest directory it fills, scaled down to the size of
+/// the directory if it's bigger (the other apps expect the thumbnails of a directory to be at most its size)
+pub fn store(filepath: &str, thumbnail_path: &Path) -> Result<(), AppError> {
+    let (uri, mtime) = match file_identity(filepath) {
+        Some(identity) => identity,
+        None => return Ok(()),
+    };
+    let png =
+        fs::read(thumbnail_path).map_err(|err| AppError::io(thumbnail_path.display(), err))?;
+    let (width, height) = match png_size(&png) {
+        Some(size) => size,
+        None => return Ok(()), // not a png
+    };
+    let (dir, size) = standard_size(width.max(height));
+    let png = match width.max(height) > size {
+        true => match scale_down(thumbnail_path, size) {
+            Some(png) => png,
+            None => return Ok(()), // not decodable by gdk-pixbuf
+        },
+        false => png,
+    };
+
+    let png = with_text_chunks(&png, &[("Thumb::URI", &uri), ("Thumb::MTime", &mtime)]);
+    let thumbnails_dir = get_thumbnails_dir()
+        .ok_or(AppError::NoUserDirectory("cache"))?
+        .join(dir);
+    fs::create_dir_all(&thumbnails_dir)
+        .map_err(|err| AppError::io(thumbnails_dir.display(), err))?;
+    let _ = fs::set_permissions(&thumbnails_dir, fs::Permissions::from_mode(0o700));
+
+    // written aside then renamed, so that other apps never read a partial thumbnail
+    let name = format!("{:x}", md5::compute(&uri));
+    let tmp_path = thumbnails_dir.join(format!("{name}.wfns.tmp"));
+    let out_path = thumbnails_dir.join(format!("{name}.png"));
+    fs::write(&tmp_path, png).map_err(|err| AppError::io(tmp_path.display(), err))?;
+    let _ = fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600));
+    fs::rename(&tmp_path, &out_path).map_err(|err| AppError::io(out_path.display(), err))
+}
+
+/// directory (and its size) of a thumbnail whose largest side is `side` pixels, the thumbnails smaller than
+/// `normal` are of small files
+fn standard_size(side: u32) -> (&'static str, u32) {
+    SIZES
+        .iter()
+        .rev()
+        .find(|(_, size)| *size <= side)
+        .unwrap_or(&SIZES[0])
+        .to_owned()
+}
+
+/// the png of a thumbnail scaled down to fit in `size` pixels
+fn scale_down(thumbnail_path: &Path, size: u32) -> Option<Vec<u8>> {
+    let size = size as i32;
+    Pixbuf::from_file_at_scale(thumbnail_path, size, size, true)
+        .and_then(|pixbuf| pixbuf.save_to_bufferv("png", &[]))
+        .ok()
+}
+
+/// iterate over the (type, data) chunks of a png
+fn chunks(png: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut rest = png.strip_prefix(PNG_SIGNATURE).unwrap_or_default();
+    std::iter::from_fn(move || {
+        let len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let chunk_type = rest.get(4..8)?;
+        let data = rest.get(8..8 + len)?;
+        rest = rest.get(12 + len..)?; // skip the crc
+        Some((chunk_type, data))
+    })
+}
+
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    let (_, ihdr) = chunks(png).find(|(t, _)| *t == b"IHDR")?;
+    let width = u32::from_be_bytes(ihdr.get(..4)?.try_into().ok()?);
+    let height = u32::from_be_bytes(ihdr.get(4..8)?.try_into().ok()?);
+    Some((width, height))
+}
+
+/// the `tEXt` key/value pairs of a png
+fn text_chunks(png: &[u8]) -> HashMap<String, String> {
+    chunks(png)
+        .filter(|(t, _)| *t == b"tEXt")
+        .filter_map(|(_, data)| {
+            let sep = data.iter().position(|b| *b == 0)?;
+            Some((
+                String::from_utf8_lossy(&data[..sep]).to_string(),
+                String::from_utf8_lossy(&data[sep + 1..]).to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// copy of a png with these `tEXt` chunks (replacing the ones with the same keys) right after its header
+fn with_text_chunks(png: &[u8], texts: &[(&str, &str)]) -> Vec<u8> {
+    let mut out = PNG_SIGNATURE.to_vec();
+    for (chunk_type, data) in chunks(png) {
+        let replaced = chunk_type == b"tEXt"
+            && texts.iter().any(|(key, _)| {
+                data.starts_with(key.as_bytes()) && data.get(key.len()) == Some(&0)
+            });
+        if !replaced {
+            push_chunk(&mut out, chunk_type, data);
+        }
+        if chunk_type == b"IHDR" {
+            for (key, value) in texts {
+                push_chunk(&mut out, b"tEXt", format!("{key}\0{value}").as_bytes());
+            }
+        }
+    }
+    out
+}
+
+fn push_chunk(out: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
+    out.extend((data.len() as u32).to_be_bytes());
+    out.extend(chunk_type);
+    out.extend(data);
+    let crc = crc32(chunk_type.iter().chain(data));
+    out.extend(crc.to_be_bytes());
+}
+
+/// crc of the png chunks (ISO 3309)
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a png made of an IHDR chunk of this size and an empty IEND
+    fn png_of(width: u32, height: u32) -> Vec<u8> {
+        let mut ihdr = [width.to_be_bytes(), height.to_be_bytes()].concat();
+        ihdr.extend([8, 6, 0, 0, 0]);
+        let mut png = PNG_SIGNATURE.to_vec();
+        push_chunk(&mut png, b"IHDR", &ihdr);
+        push_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn computes_the_png_crc() {
+        assert_eq!(crc32(b"".iter()), 0);
+        assert_eq!(crc32(b"123456789".iter()), 0xcbf4_3926);
+        // the crc of an empty IEND chunk found at the end of every png
+        assert_eq!(crc32(b"IEND".iter()), 0xae42_6082);
+    }
+
+    #[test]
+    fn reads_the_png_size() {
+        assert_eq!(png_size(&png_of(600, 338)), Some((600, 338)));
+        assert_eq!(png_size(b"GIF89a"), None);
+        // truncated in the header
+        assert_eq!(png_size(&png_of(600, 338)[..20]), None);
+    }
+
+    #[test]
+    fn writes_text_chunks_after_the_header() {
+        let png = with_text_chunks(&png_of(256, 144), &[("Thumb::URI", "file:///a.mp4")]);
+        let types = chunks(&png).map(|(t, _)| t.to_vec()).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [b"IHDR".to_vec(), b"tEXt".to_vec(), b"IEND".to_vec()]
+        );
+        assert_eq!(png_size(&png), Some((256, 144)));
+
+        let texts = text_chunks(&png);
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts["Thumb::URI"], "file:///a.mp4");
+    }
+
+    #[test]
+    fn writes_valid_crcs() {
+        let png = with_text_chunks(&png_of(128, 128), &[("Thumb::MTime", "1700000000")]);
+        let mut rest = &png[PNG_SIGNATURE.len()..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            assert_eq!(crc, crc32(rest[4..8 + len].iter()));
+            rest = &rest[12 + len..];
+        }
+    }
+
+    #[test]
+    fn replaces_the_text_chunks_with_the_same_key() {
+        let png = with_text_chunks(
+            &png_of(128, 72),
+            &[("Thumb::URI", "file:///a.mp4"), ("Thumb::MTime", "1")],
+        );
+        let png = with_text_chunks(&png, &[("Thumb::MTime", "2")]);
+        let texts = text_chunks(&png);
+        assert_eq!(texts.len(), 2);
+        assert_eq!(texts["Thumb::URI"], "file:///a.mp4");
+        assert_eq!(texts["Thumb::MTime"], "2");
+        // a key only starting like the replaced one is kept
+        let png = with_text_chunks(&png, &[("Thumb::URIx", "other")]);
+        assert_eq!(text_chunks(&png)["Thumb::URI"], "file:///a.mp4");
+    }
+
+    #[test]
+    fn puts_the_thumbnails_in_the_largest_directory_they_fill() {
+        assert_eq!(standard_size(600), ("x-large", 512));
+        assert_eq!(standard_size(512), ("x-large", 512));
+        assert_eq!(standard_size(300), ("large", 256));
+        assert_eq!(standard_size(2000), ("xx-large", 1024));
+        // the thumbnail of a small file is smaller than its directory
+        assert_eq!(standard_size(64), ("normal", 128));
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::app::error::AppError;
+
+/// the least recently used thumbnails are evicted past this size
+pub const MAX_CACHE_SIZE: u64 = 200 * 1024 * 1024;
+
+/// return the path to the thumbnails cache directory (and ensure that it exists)
+fn get_cache_dir() -> Result<PathBuf, AppError> {
+    let mut cache_path = dirs::cache_dir().ok_or(AppError::NoUserDirectory("cache"))?;
+
+    cache_path.push("wfns_manager");
+    fs::create_dir_all(&cache_path).map_err(|err| AppError::io(cache_path.display(), err))?;
+    Ok(cache_path)
+}
+
+/// path of the cached thumbnail of a file, the key changes with the file path, size and modification date
+/// so that two files with the same name don't share their thumbnail and a modified file gets a new one
+pub fn get_thumbnail_path(filepath: &str) -> Result<PathBuf, AppError> {
+    let metadata = fs::metadata(filepath).map_err(|err| AppError::io(filepath, err))?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let key = md5::compute(format!("{filepath}\0{mtime}\0{}", metadata.len()));
+    Ok(get_cache_dir()?.join(format!("{key:x}.png")))
+}
+
+/// return the cached thumbnail if there is one, marking it as recently used
+pub fn lookup(thumbnail_path: &Path) -> Option<PathBuf> {
+    let file = File::options().write(true).open(thumbnail_path).ok()?;
+    let _ = file.set_modified(SystemTime::now());
+    Some(thumbnail_path.to_path_buf())
+}
+
+/// move a freshly generated thumbnail into the cache, then evict the least recently used thumbnails
+/// if the cache grew too big
+pub fn insert(generated_path: &Path, thumbnail_path: &Path) -> Result<(), AppError> {
+    fs::rename(generated_path, thumbnail_path)
+        .map_err(|err| AppError::io(thumbnail_path.display(), err))?;
+    let cache_dir = match thumbnail_path.parent() {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+
+    let mut entries = cache_entries(cache_dir)?;
+    let mut size = entries.iter().map(|(_, len, _)| len).sum::<u64>();
+    if size <= MAX_CACHE_SIZE {
+        return Ok(());
+    }
+    entries.sort_by_key(|(_, _, last_used)| *last_used);
+    for (path, len, _) in entries {
+        if size <= MAX_CACHE_SIZE {
+            break;
+        }
+        if path != thumbnail_path && fs::remove_file(&path).is_ok() {
+            size -= len;
+        }
+    }
+    Ok(())
+}
+
+/// total size in bytes of the cached thumbnails
+pub fn size() -> Result<u64, AppError> {
+    Ok(cache_entries(&get_cache_dir()?)?
+        .iter()
+        .map(|(_, len, _)| len)
+        .sum())
+}
+
+/// remove every cached thumbnail, the ones that can't be removed don't stop the others from being removed
+pub fn clear() -> Result<(), AppError> {
+    clear_dir(&get_cache_dir()?)
+}
+
+fn clear_dir(cache_dir: &Path) -> Result<(), AppError> {
+    let errors = cache_entries(cache_dir)?
+        .into_iter()
+        .filter_map(|(path, _, _)| {
+            fs::remove_file(&path)
+                .err()
+                .map(|err| AppError::io(path.display(), err))
+        })
+        .collect::<Vec<_>>();
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(AppError::CacheNotCleared { errors }),
+    }
+}
+
+/// the cached thumbnails with their size and last use, without the ones being generated
+fn cache_entries(cache_dir: &Path) -> Result<Vec<(PathBuf, u64, SystemTime)>, AppError> {
+    let entries = fs::read_dir(cache_dir).map_err(|err| AppError::io(cache_dir.display(), err))?;
+    Ok(entries
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_name().to_string_lossy().ends_with(".tmp.png"))
+        .filter_map(|e| {
+            let metadata = e.metadata().ok().filter(|m| m.is_file())?;
+            let last_used = metadata.modified().unwrap_or(UNIX_EPOCH);
+            Some((e.path(), metadata.len(), last_used))
+        })
+        .collect())
+}
+
+/// human readable size
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{b} B"),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process, time::Duration};
+
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn temp_cache_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("wfns_thumb_cache_{name}_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// a sparse file of `len` bytes last used `age` seconds ago
+    fn thumbnail(dir: &Path, name: &str, len: u64, age: u64) -> PathBuf {
+        let path = dir.join(name);
+        let file = File::create(&path).unwrap();
+        file.set_len(len).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age))
+            .unwrap();
+        path
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_thumbnails_past_the_cap() {
+        let dir = temp_cache_dir("evict");
+        for (i, age) in [400, 300, 200, 100].into_iter().enumerate() {
+            thumbnail(&dir, &format!("{i}.png"), 80 * MB, age);
+        }
+        // used again, it's now the most recent one
+        lookup(&dir.join("0.png")).unwrap();
+
+        let generated = thumbnail(&dir, "new.tmp.png", 30 * MB, 0);
+        insert(&generated, &dir.join("new.png")).unwrap();
+        assert_eq!(names(&dir), ["0.png", "3.png", "new.png"]);
+        let size = cache_entries(&dir)
+            .unwrap()
+            .iter()
+            .map(|(_, len, _)| len)
+            .sum::<u64>();
+        assert!(size <= MAX_CACHE_SIZE);
+
+        // nothing is evicted below the cap
+        let generated = thumbnail(&dir, "small.tmp.png", MB, 0);
+        insert(&generated, &dir.join("small.png")).unwrap();
+        assert_eq!(names(&dir), ["0.png", "3.png", "new.png", "small.png"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn clear_keeps_the_thumbnails_being_generated() {
+        let dir = temp_cache_dir("clear");
+        thumbnail(&dir, "a.png", 10, 0);
+        thumbnail(&dir, "b.png", 10, 0);
+        thumbnail(&dir, "c.tmp.png", 10, 0);
+        clear_dir(&dir).unwrap();
+        assert_eq!(names(&dir), ["c.tmp.png"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
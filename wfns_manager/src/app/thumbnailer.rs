@@ -0,0 +1,334 @@
+use std::{
+    env, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Condvar, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use relm4::gtk::{
+    gio::{self, prelude::*},
+    glib,
+};
+
+use crate::app::error::AppError;
+
+/// a thumbnailer taking longer than this is killed
+const THUMBNAILER_TIMEOUT: Duration = Duration::from_secs(15);
+/// maximum number of thumbnailers running at the same time
+const MAX_RUNNING_THUMBNAILERS: usize = 2;
+
+/// icon shown when no thumbnailer can render a file
+pub const FALLBACK_ICON: &str = "video-x-generic-symbolic";
+
+static RUNNING: Mutex<usize> = Mutex::new(0);
+static SLOT_FREED: Condvar = Condvar::new();
+static THUMBNAILERS: OnceLock<Vec<Box<dyn Thumbnailer>>> = OnceLock::new();
+
+/// A way to render the thumbnail of a file
+pub trait Thumbnailer: Send + Sync {
+    /// name shown in the error messages
+    fn name(&self) -> &str;
+
+    /// whether it can render the files of this MIME type
+    fn supports(&self, mime_type: &str) -> bool;
+
+    /// write a PNG thumbnail of `size` pixels wide of `filepath` to `out_path`
+    fn generate(&self, filepath: &str, out_path: &Path, size: u32) -> Result<(), AppError>;
+}
+
+/// extract the first frame of a video with the ffmpeg CLI
+struct Ffmpeg;
+
+impl Thumbnailer for Ffmpeg {
+    fn name(&self) -> &str {
+        "ffmpeg"
+    }
+
+    fn supports(&self, mime_type: &str) -> bool {
+        mime_type.starts_with("video/")
+    }
+
+    fn generate(&self, filepath: &str, out_path: &Path, size: u32) -> Result<(), AppError> {
+        let args = [
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-y",
+            "-i",
+            filepath,
+            "-vf",
+            &format!("scale={size}:-1"),
+            "-frames:v",
+            "1",
+            &out_path.to_string_lossy(),
+        ]
+        .map(String::from);
+        run("ffmpeg", &args, filepath)
+    }
+}
+
+/// a `.thumbnailer` file installed on the system (the ones used by the file managers)
+struct Desktop {
+    name: String,
+    exec: Vec<String>,
+    mime_types: Vec<String>,
+}
+
+impl Desktop {
+    fn parse(path: &Path) -> Option<Self> {
+        let datas = fs::read_to_string(path).ok()?;
+        let (mut exec, mut mime_types) = (None, vec![]);
+        for line in datas.lines() {
+            match line.split_once('=') {
+                // the arguments can be quoted like in a shell
+                Some(("Exec", value)) => {
+                    exec = glib::shell_parse_argv(value).ok().map(|args| {
+                        args.into_iter()
+                            .map(|a| a.to_string_lossy().to_string())
+                            .collect::<Vec<_>>()
+                    })
+                }
+                Some(("MimeType", value)) => {
+                    mime_types = value
+                        .split(';')
+                        .filter(|m| !m.is_empty())
+                        .map(String::from)
+                        .collect()
+                }
+                _ => continue,
+            }
+        }
+
+        Some(Self {
+            name: path.file_stem()?.to_string_lossy().to_string(),
+            exec: exec.filter(|e| !e.is_empty())?,
+            mime_types,
+        })
+    }
+
+    /// the installed thumbnailers, in the freedesktop data directories
+    fn load_all() -> Vec<Self> {
+        let data_dirs = env::var("XDG_DATA_DIRS")
+            .ok()
+            .filter(|d| !d.is_empty())
+            .unwrap_or("/usr/local/share:/usr/share".to_string());
+        let dirs = dirs::data_dir()
+            .into_iter()
+            .chain(data_dirs.split(':').map(PathBuf::from))
+            .map(|d| d.join("thumbnailers"));
+
+        let mut thumbnailers = dirs
+            .filter_map(|d| fs::read_dir(d).ok())
+            .flat_map(|entries| entries.filter_map(|e| e.ok()))
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "thumbnailer"))
+            .filter_map(|e| Self::parse(&e.path()))
+            .collect::<Vec<_>>();
+        // the first data directories take precedence
+        let mut names = vec![];
+        thumbnailers.retain(|t| {
+            let is_new = !names.contains(&t.name);
+            names.push(t.name.clone());
+            is_new
+        });
+        thumbnailers
+    }
+}
+
+impl Thumbnailer for Desktop {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn supports(&self, mime_type: &str) -> bool {
+        self.mime_types.iter().any(|m| m == mime_type)
+    }
+
+    fn generate(&self, filepath: &str, out_path: &Path, size: u32) -> Result<(), AppError> {
+        let uri = gio::File::for_path(filepath).uri().to_string();
+        let out_path = out_path.to_string_lossy();
+        let args = self
+            .exec
+            .iter()
+            .map(|arg| expand_field_codes(arg, filepath, &uri, &out_path, size))
+            .collect::<Vec<_>>();
+        run(&args[0], &args[1..], filepath)
+    }
+}
+
+/// expand the field codes of the spec in a single pass (so that a path containing one is left as is): %i input
+/// path, %u input URI, %o output path, %s size and %% for a percent sign
+fn expand_field_codes(arg: &str, filepath: &str, uri: &str, out_path: &str, size: u32) -> String {
+    let mut expanded = String::with_capacity(arg.len());
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('i') => expanded.push_str(filepath),
+            Some('u') => expanded.push_str(uri),
+            Some('o') => expanded.push_str(out_path),
+            Some('s') => expanded.push_str(&size.to_string()),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
+
+/// ffmpeg then the installed thumbnailers. GStreamer isn't used directly: `gst-launch-1.0` is a debugging tool
+/// that parses its arguments as a pipeline (the paths with spaces or a `!` break it), its thumbnailers are
+/// used through their `.thumbnailer` files instead (e.g. totem or gst-thumbnailer)
+fn thumbnailers() -> &'static [Box<dyn Thumbnailer>] {
+    THUMBNAILERS.get_or_init(|| {
+        let mut thumbnailers: Vec<Box<dyn Thumbnailer>> = vec![Box::new(Ffmpeg)];
+        for desktop in Desktop::load_all() {
+            thumbnailers.push(Box::new(desktop));
+        }
+        thumbnailers
+    })
+}
+
+/// write a PNG thumbnail of `filepath` to `out_path` with the first thumbnailer (supporting its MIME type)
+/// that succeeds, blocks while too many thumbnailers are already running
+pub fn generate_thumbnail(filepath: &str, out_path: &Path, size: u32) -> Result<(), AppError> {
+    let (content_type, _) = gio::content_type_guess(Some(filepath), &[]);
+    let mime_type = gio::content_type_get_mime_type(&content_type)
+        .map(|m| m.to_string())
+        .unwrap_or_default();
+
+    let _slot = RunningSlot::acquire();
+    let mut last_error = None;
+    for thumbnailer in thumbnailers().iter().filter(|t| t.supports(&mime_type)) {
+        match thumbnailer.generate(filepath, out_path, size) {
+            Ok(()) if out_path.exists() => return Ok(()),
+            Ok(()) => {
+                last_error = Some(AppError::ThumbnailerFailed {
+                    program: thumbnailer.name().to_string(),
+                    path: filepath.to_string(),
+                    stderr: "no thumbnail was written".to_string(),
+                })
+            }
+            // a missing program shouldn't hide the failure of another thumbnailer
+            Err(err @ AppError::ThumbnailerMissing { .. }) if last_error.is_none() => {
+                last_error = Some(err)
+            }
+            Err(AppError::ThumbnailerMissing { .. }) => {}
+            Err(err) => last_error = Some(err),
+        }
+    }
+
+    Err(match last_error {
+        Some(AppError::ThumbnailerMissing { .. }) | None => AppError::NoThumbnailer {
+            path: filepath.to_string(),
+            mime_type,
+        },
+        Some(err) => err,
+    })
+}
+
+/// run a thumbnailer program, killing it if it takes too long
+fn run(program: &str, args: &[String], filepath: &str) -> Result<(), AppError> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => AppError::ThumbnailerMissing {
+                program: program.to_string(),
+            },
+            _ => AppError::io(program, err),
+        })?;
+
+    // read stderr on the side so that a verbose program doesn't block on a full pipe
+    let stderr = child.stderr.take().map(|mut pipe| {
+        thread::spawn(move || {
+            let mut output = String::new();
+            let _ = pipe.read_to_string(&mut output);
+            output
+        })
+    });
+
+    let deadline = Instant::now() + THUMBNAILER_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(AppError::ThumbnailerTimeout {
+                    program: program.to_string(),
+                    path: filepath.to_string(),
+                });
+            }
+            Err(err) => return Err(AppError::io(program, err)),
+        }
+    };
+
+    if !status.success() {
+        return Err(AppError::ThumbnailerFailed {
+            program: program.to_string(),
+            path: filepath.to_string(),
+            stderr: stderr
+                .and_then(|handle| handle.join().ok())
+                .unwrap_or_default(),
+        });
+    }
+    Ok(())
+}
+
+/// one of the `MAX_RUNNING_THUMBNAILERS` slots, released when dropped
+struct RunningSlot;
+
+impl RunningSlot {
+    fn acquire() -> Self {
+        let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+        while *running >= MAX_RUNNING_THUMBNAILERS {
+            running = SLOT_FREED.wait(running).unwrap_or_else(|e| e.into_inner());
+        }
+        *running += 1;
+        Self
+    }
+}
+
+impl Drop for RunningSlot {
+    fn drop(&mut self) {
+        let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+        *running -= 1;
+        SLOT_FREED.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_codes_are_expanded_once() {
+        let expand = |arg| {
+            expand_field_codes(
+                arg,
+                "/v/50%off.mp4",
+                "file:///v/50%25off.mp4",
+                "/t/%s.png",
+                256,
+            )
+        };
+        assert_eq!(expand("%i"), "/v/50%off.mp4");
+        assert_eq!(expand("--uri=%u"), "--uri=file:///v/50%25off.mp4");
+        assert_eq!(expand("-o%o"), "-o/t/%s.png");
+        assert_eq!(expand("-s%s"), "-s256");
+        assert_eq!(expand("100%%"), "100%");
+    }
+}
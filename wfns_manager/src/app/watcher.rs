@@ -0,0 +1,101 @@
+use std::{collections::HashMap, path::Path, rc::Rc};
+
+use relm4::gtk::gio::{self, prelude::*};
+
+/// change in the open root, the paths are relative to the root
+#[derive(Debug)]
+pub enum WatchEvent {
+    Created(String),
+    Deleted(String),
+}
+
+/// Watches the directories of the open root (the root itself and, when scanned recursively, the directories
+/// the scan goes into), the monitors are cancelled when the watcher is dropped
+pub struct FolderWatcher {
+    root_path: String,
+    monitors: HashMap<String, gio::FileMonitor>,
+    on_event: Rc<dyn Fn(WatchEvent)>,
+}
+
+impl FolderWatcher {
+    pub fn new(root_path: &str, on_event: impl Fn(WatchEvent) + 'static) -> Self {
+        Self {
+            root_path: root_path.to_string(),
+            monitors: HashMap::new(),
+            on_event: Rc::new(on_event),
+        }
+    }
+
+    pub fn root_path(&self) -> &str {
+        &self.root_path
+    }
+
+    /// start watching these directories (relative to the root, "" being the root itself) if not already watched
+    pub fn watch(&mut self, rel_dirs: impl IntoIterator<Item = String>) {
+        for rel_dir in rel_dirs {
+            if self.monitors.contains_key(&rel_dir) {
+                continue;
+            }
+
+            let dir = Path::new(&self.root_path).join(&rel_dir);
+            let monitor = match gio::File::for_path(dir)
+                .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+            {
+                Ok(m) => m,
+                Err(_) => continue, // not watched, the changes will be seen at the next scan
+            };
+
+            let (root_path, on_event) = (self.root_path.clone(), self.on_event.clone());
+            monitor.connect_changed(move |_, file, other_file, event| {
+                let rel_path = |f: &gio::File| {
+                    f.path().and_then(|p| {
+                        p.strip_prefix(&root_path)
+                            .ok()
+                            .map(|rel| rel.to_string_lossy().to_string())
+                    })
+                };
+                match event {
+                    gio::FileMonitorEvent::Created | gio::FileMonitorEvent::MovedIn => {
+                        if let Some(rel) = rel_path(file) {
+                            on_event(WatchEvent::Created(rel));
+                        }
+                    }
+                    gio::FileMonitorEvent::Deleted | gio::FileMonitorEvent::MovedOut => {
+                        if let Some(rel) = rel_path(file) {
+                            on_event(WatchEvent::Deleted(rel));
+                        }
+                    }
+                    gio::FileMonitorEvent::Renamed => {
+                        if let Some(rel) = rel_path(file) {
+                            on_event(WatchEvent::Deleted(rel));
+                        }
+                        if let Some(rel) = other_file.and_then(rel_path) {
+                            on_event(WatchEvent::Created(rel));
+                        }
+                    }
+                    _ => {}
+                }
+            });
+            self.monitors.insert(rel_dir, monitor);
+        }
+    }
+
+    /// stop watching a deleted directory and everything inside of it
+    pub fn unwatch(&mut self, rel_path: &str) {
+        self.monitors.retain(|rel_dir, monitor| {
+            let gone = rel_dir == rel_path || rel_dir.starts_with(&format!("{rel_path}/"));
+            if gone {
+                monitor.cancel();
+            }
+            !gone
+        });
+    }
+}
+
+impl Drop for FolderWatcher {
+    fn drop(&mut self) {
+        self.monitors.values().for_each(|m| {
+            m.cancel();
+        });
+    }
+}
@@ -0,0 +1,108 @@
+use nanorand::{Rng, WyRand};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// an item never shown is considered as last shown a year ago
+const NEVER_SHOWN_AGE: u64 = 365 * 24 * 3600;
+
+/// how the next item of a root is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PickMode {
+    /// uniform shuffle bag, items don't repeat until all of them have been seen
+    #[default]
+    Shuffle,
+    /// the chance of an item grows with the time since it was last shown and with its rating
+    Weighted,
+}
+
+/// weight of an item in the weighted pick mode, each factor is kept to explain why an item came up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weight {
+    /// seconds since the item was last shown, `None` if it never was
+    pub since_last_shown: Option<u64>,
+    pub time_factor: f64,
+    pub count_factor: f64,
+    pub rating_factor: f64,
+}
+
+impl Weight {
+    /// `count` is the number of elements inside of the item (e.g. images in a subfolder), only taken into
+    /// account when the user chose to favor bigger items. The better rated items come up more often
+    pub fn new(
+        last_shown: Option<u64>,
+        count: Option<usize>,
+        rating: Option<u8>,
+        now: u64,
+    ) -> Self {
+        let since_last_shown = last_shown.map(|ls| now.saturating_sub(ls));
+        let age_hours = since_last_shown.unwrap_or(NEVER_SHOWN_AGE) as f64 / 3600.0;
+        Self {
+            since_last_shown,
+            // logarithmic growth: 1 right after being shown, ~4 after a day, ~10 after a year
+            time_factor: 1.0 + (1.0 + age_hours).ln(),
+            count_factor: count.map(|c| (2.0 + c as f64).ln()).unwrap_or(1.0),
+            // 3 stars weigh as much as no rating, 1 star a third of it and 5 stars 5/3 of it
+            rating_factor: rating.map(|r| r as f64 / 3.0).unwrap_or(1.0),
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.time_factor * self.count_factor * self.rating_factor
+    }
+}
+
+/// weight and odds of the last weighted pick, shown in the details view
+#[derive(Debug, Clone)]
+pub struct PickDetails {
+    pub key: String,
+    pub weight: Weight,
+    pub probability: f64,
+    pub candidates: usize,
+}
+
+/// draw an index with a probability proportional to its weight
+pub fn draw(weights: &[f64]) -> Option<usize> {
+    let total = weights.iter().sum::<f64>();
+    if weights.is_empty() || total <= 0.0 {
+        return None;
+    }
+
+    let mut target = WyRand::new().generate::<f64>() * total;
+    for (i, w) in weights.iter().enumerate() {
+        if target < *w {
+            return Some(i);
+        }
+        target -= w;
+    }
+    Some(weights.len() - 1) // float rounding
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// human readable duration since the last time an item was shown
+pub fn format_age(since_last_shown: Option<u64>) -> String {
+    match since_last_shown {
+        None => "never".to_string(),
+        Some(s) if s < 3600 => format!("{} minutes ago", s / 60),
+        Some(s) if s < 24 * 3600 => format!("{} hours ago", s / 3600),
+        Some(s) => format!("{} days ago", s / (24 * 3600)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn favors_the_best_rated_items() {
+        let weight = |rating| Weight::new(Some(0), None, rating, 3600).value();
+        assert_eq!(weight(Some(3)), weight(None));
+        assert!(weight(Some(1)) < weight(None));
+        assert!(weight(Some(5)) > weight(Some(4)));
+        assert!((weight(Some(5)) / weight(Some(1)) - 5.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,468 @@
+use std::{path::Path, process::ExitCode, sync::atomic::AtomicBool};
+
+use crate::app::{
+    backend::{normalize_path, root_dir, AnyItem, AppFolderManager, Media, MediaType, SubFolder},
+    cover,
+    error::AppError,
+    preferences::{AppMode, AppPreferences},
+    scanner::{self, ScannedItems},
+};
+
+const USAGE: &str = "Usage:
+  wfns_manager [<folder>...]                   open the app, picking from these folders
+  wfns_manager pick <folder>... [options]      pick random items, like the app does
+  wfns_manager list <folder>... [options]      list the items of the folders
+  wfns_manager favorites list
+  wfns_manager favorites add <folder>...
+  wfns_manager favorites remove <folder>...
+
+Options:
+  --mode <subfolders|images|videos|all>        kind of items (default: the one the app would choose)
+  --count <n>                                  number of items to pick (default: 1), fewer once every item
+                                               of the round has been picked
+  --seed <n>                                   shuffle the folders with this seed for this run only, nothing
+                                               is remembered (not in weighted mode)
+  --json                                       print the items as JSON";
+
+/// the command line asked for something else than opening the app
+pub enum CliCommand {
+    Pick(Vec<String>, CliOptions),
+    List(Vec<String>, CliOptions),
+    FavoritesList,
+    FavoritesAdd(Vec<String>),
+    FavoritesRemove(Vec<String>),
+}
+
+#[derive(Debug, Default)]
+pub struct CliOptions {
+    app_mode: Option<AppMode>,
+    count: usize,
+    seed: Option<u64>,
+    json: bool,
+}
+
+/// an item as printed by the command line
+struct CliItem {
+    kind: &'static str,
+    name: String,
+    path: String,
+    /// the image shown for a subfolder
+    cover: Option<String>,
+}
+
+impl From<&SubFolder> for CliItem {
+    fn from(sf: &SubFolder) -> Self {
+        Self {
+            kind: "subfolder",
+            name: sf.name.clone(),
+            path: sf.get_path(),
+            cover: sf
+                .thumbnail
+                .as_ref()
+                .map(|t| format!("{}/{t}", sf.get_path())),
+        }
+    }
+}
+
+impl From<&Media> for CliItem {
+    fn from(m: &Media) -> Self {
+        Self {
+            kind: match m.media_type {
+                MediaType::Image => "image",
+                MediaType::Video => "video",
+            },
+            name: m.name.clone(),
+            path: m.filepath.clone(),
+            cover: None,
+        }
+    }
+}
+
+impl From<&AnyItem> for CliItem {
+    fn from(item: &AnyItem) -> Self {
+        match item {
+            AnyItem::SubFolder(sf) => sf.into(),
+            AnyItem::Media(m) => m.into(),
+        }
+    }
+}
+
+/// parse the command line, `Ok(None)` means that the app must be opened (with the folders of the command
+/// line). Errors are the message to print
+pub fn parse(args: &[String]) -> Result<Option<CliCommand>, String> {
+    let (command, args) = match args.split_first() {
+        Some((c, a)) => (c.as_str(), a),
+        None => return Ok(None),
+    };
+    match command {
+        "pick" | "list" => {
+            let (folders, options) = parse_options(args)?;
+            if folders.is_empty() {
+                return Err(format!("`{command}` needs at least one folder"));
+            }
+            Ok(Some(match command {
+                "pick" => CliCommand::Pick(folders, options),
+                _ => CliCommand::List(folders, options),
+            }))
+        }
+        "favorites" => match args.split_first() {
+            Some((sub, [])) if sub == "list" => Ok(Some(CliCommand::FavoritesList)),
+            Some((sub, folders)) if sub == "add" && !folders.is_empty() => {
+                Ok(Some(CliCommand::FavoritesAdd(folders.to_vec())))
+            }
+            Some((sub, folders)) if sub == "remove" && !folders.is_empty() => {
+                Ok(Some(CliCommand::FavoritesRemove(folders.to_vec())))
+            }
+            _ => Err("expected `favorites list`, `favorites add <folder>...` or `favorites remove <folder>...`".to_string()),
+        },
+        "-h" | "--help" | "help" => Err(String::new()),
+        // folders to open in the app
+        path if path.contains("://") || Path::new(path).exists() => Ok(None),
+        _ => Err(format!("unknown command `{command}`")),
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<(Vec<String>, CliOptions), String> {
+    let mut folders = vec![];
+    let mut options = CliOptions {
+        count: 1,
+        ..Default::default()
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("`{name}` needs a value"));
+        match arg.as_str() {
+            "--mode" => {
+                let mode = value("--mode")?;
+                options.app_mode =
+                    Some(AppMode::from_name(mode).ok_or(format!("unknown mode `{mode}`"))?)
+            }
+            "--count" => {
+                options.count = value("--count")?
+                    .parse()
+                    .ok()
+                    .filter(|c| *c > 0)
+                    .ok_or("`--count` must be a positive number")?
+            }
+            "--seed" => {
+                options.seed = Some(
+                    value("--seed")?
+                        .parse()
+                        .map_err(|_| "`--seed` must be a number")?,
+                )
+            }
+            "--json" => options.json = true,
+            option if option.starts_with("--") => return Err(format!("unknown option `{option}`")),
+            folder => folders.push(folder.to_string()),
+        }
+    }
+    Ok((folders, options))
+}
+
+/// run a command without opening the app
+pub fn run(command: CliCommand) -> ExitCode {
+    let result = match command {
+        CliCommand::Pick(folders, options) => pick(folders, &options),
+        CliCommand::List(folders, options) => list(folders, &options),
+        CliCommand::FavoritesList => favorites_list(),
+        CliCommand::FavoritesAdd(folders) => favorites_edit(folders, true),
+        CliCommand::FavoritesRemove(folders) => favorites_edit(folders, false),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}\n{}", err.details());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// print the usage, along with what was wrong with the command line
+pub fn usage(error: &str) -> ExitCode {
+    match error.is_empty() {
+        true => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        false => {
+            eprintln!("error: {error}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// scan the folders as the app does (same deck, filter and scan options), return the mode to use
+fn open_folders(
+    folders: Vec<String>,
+    options: &CliOptions,
+) -> Result<(AppFolderManager, AppMode), AppError> {
+    let roots = folders
+        .iter()
+        .map(|f| root_dir(f))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut folder = AppFolderManager::new(roots);
+    if let Some(seed) = options.seed {
+        folder.use_seed_once(seed);
+    }
+
+    let mut items = ScannedItems::default();
+    let recommanded_app_mode = scanner::scan_roots(
+        &folder.roots,
+        &folder.scan_options(),
+        &AtomicBool::new(false),
+        |batch, _| items.extend(batch),
+        |err| eprintln!("warning: {err}"),
+    )?;
+    let app_mode = options
+        .app_mode
+        .clone()
+        .unwrap_or(recommanded_app_mode.unwrap_or_default());
+    folder.add_items(items, app_mode.clone());
+    Ok((folder, app_mode))
+}
+
+/// pick items from the deck of the folders, they are marked as seen just like in the app (unless a seed is given)
+fn pick(folders: Vec<String>, options: &CliOptions) -> Result<(), AppError> {
+    let (mut folder, app_mode) = open_folders(folders, options)?;
+    folder.finish_scan();
+    folder.reset_curr_index(app_mode.clone());
+
+    // an item is never listed twice: the picks stop at the end of the round, and an item drawn again by the
+    // weighted mode is skipped (giving up after as many draws as there are items)
+    let mut picked: Vec<CliItem> = vec![];
+    let mut draws = deck_len(&folder, &app_mode);
+    while let Some(item) = curr_item(&folder, &app_mode) {
+        if !picked.iter().any(|p| p.path == item.path) {
+            picked.push(item);
+        }
+        draws = draws.saturating_sub(1);
+        if picked.len() == options.count
+            || draws == 0
+            || folder.next_in_round(app_mode.clone()).is_err()
+        {
+            break;
+        }
+    }
+    print_items(&picked, options.json);
+    folder.flush_deck()
+}
+
+/// list the items of the folders (matching their filter) in natural order
+fn list(folders: Vec<String>, options: &CliOptions) -> Result<(), AppError> {
+    let (folder, app_mode) = open_folders(folders, options)?;
+    let mut items = match app_mode {
+        AppMode::SubFolders => folder.subfolders.iter().map(CliItem::from).collect(),
+        AppMode::Images => folder.images.iter().map(CliItem::from).collect(),
+        AppMode::Videos => folder.videos.iter().map(CliItem::from).collect(),
+        AppMode::All => folder.all.iter().map(CliItem::from).collect::<Vec<_>>(),
+    };
+    items.sort_by(|a, b| cover::natural_cmp(&a.path, &b.path));
+    print_items(&items, options.json);
+    Ok(())
+}
+
+fn deck_len(folder: &AppFolderManager, app_mode: &AppMode) -> usize {
+    match app_mode {
+        AppMode::SubFolders => folder.subfolders.len(),
+        AppMode::Images => folder.images.len(),
+        AppMode::Videos => folder.videos.len(),
+        AppMode::All => folder.all.len(),
+    }
+}
+
+fn curr_item(folder: &AppFolderManager, app_mode: &AppMode) -> Option<CliItem> {
+    let f = folder;
+    match app_mode {
+        AppMode::SubFolders => f.subfolders.get(f.curr).map(CliItem::from),
+        AppMode::Images => f.images.get(f.curr).map(CliItem::from),
+        AppMode::Videos => f.videos.get(f.curr).map(CliItem::from),
+        AppMode::All => f.all.get(f.curr).map(CliItem::from),
+    }
+}
+
+/// a corrupted settings file is set aside and replaced, like in the app (if it can't be set aside, nothing is
+/// done rather than overwriting it)
+fn load_prefs() -> Result<AppPreferences, AppError> {
+    match AppPreferences::load() {
+        Err(
+            err @ AppError::ConfigCorrupt {
+                backup: Some(_), ..
+            },
+        ) => {
+            eprintln!("warning: {err}\n{}", err.details());
+            Ok(AppPreferences::fallback(&err))
+        }
+        result => result,
+    }
+}
+
+fn favorites_list() -> Result<(), AppError> {
+    let prefs = load_prefs()?;
+    for fav in &prefs.favs_folders {
+        match &fav.alias {
+            Some(alias) => println!("{}\t{alias}", fav.path),
+            None => println!("{}", fav.path),
+        }
+    }
+    Ok(())
+}
+
+fn favorites_edit(folders: Vec<String>, add: bool) -> Result<(), AppError> {
+    let mut prefs = load_prefs()?;
+    for folder in folders {
+        match add {
+            true => prefs.add_favorite(&root_dir(&folder)?),
+            // a removed folder may not exist anymore
+            false => prefs.remove_favorite(&normalize_path(&folder)),
+        };
+    }
+    prefs.save()
+}
+
+fn print_items(items: &[CliItem], json: bool) {
+    if !json {
+        for item in items {
+            match &item.cover {
+                Some(cover) => println!("{}\t{cover}", item.path),
+                None => println!("{}", item.path),
+            }
+        }
+        return;
+    }
+
+    let objects = items
+        .iter()
+        .map(|item| {
+            format!(
+                "{{\"kind\":\"{}\",\"name\":{},\"path\":{},\"cover\":{}}}",
+                item.kind,
+                json_string(&item.name),
+                json_string(&item.path),
+                item.cover
+                    .as_deref()
+                    .map(json_string)
+                    .unwrap_or("null".to_string())
+            )
+        })
+        .collect::<Vec<_>>();
+    println!("[{}]", objects.join(","));
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn parse_error(a: &[&str]) -> String {
+        match parse(&args(a)) {
+            Err(err) => err,
+            Ok(_) => panic!("{a:?} should be rejected"),
+        }
+    }
+
+    #[test]
+    fn opens_the_app_without_command() {
+        assert!(matches!(parse(&[]), Ok(None)));
+        assert!(matches!(parse(&args(&["/"])), Ok(None)));
+        assert!(matches!(parse(&args(&["file:///tmp"])), Ok(None)));
+    }
+
+    #[test]
+    fn parses_the_options() {
+        let command = parse(&args(&[
+            "pick", "/a", "--mode", "images", "--count", "3", "--seed", "42", "/b", "--json",
+        ]));
+        match command {
+            Ok(Some(CliCommand::Pick(folders, options))) => {
+                assert_eq!(folders, args(&["/a", "/b"]));
+                assert_eq!(options.app_mode, Some(AppMode::Images));
+                assert_eq!(options.count, 3);
+                assert_eq!(options.seed, Some(42));
+                assert!(options.json);
+            }
+            _ => panic!("expected a pick command"),
+        }
+        match parse(&args(&["list", "/a"])) {
+            Ok(Some(CliCommand::List(_, options))) => {
+                assert_eq!(options.count, 1);
+                assert_eq!(options.app_mode, None);
+            }
+            _ => panic!("expected a list command"),
+        }
+        assert!(matches!(
+            parse(&args(&["favorites", "list"])),
+            Ok(Some(CliCommand::FavoritesList))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        assert_eq!(parse_error(&["pick"]), "`pick` needs at least one folder");
+        assert_eq!(
+            parse_error(&["list", "--json"]),
+            "`list` needs at least one folder"
+        );
+        assert_eq!(
+            parse_error(&["pick", "/a", "--mode", "music"]),
+            "unknown mode `music`"
+        );
+        assert_eq!(
+            parse_error(&["pick", "/a", "--mode"]),
+            "`--mode` needs a value"
+        );
+        assert_eq!(
+            parse_error(&["pick", "/a", "--count", "0"]),
+            "`--count` must be a positive number"
+        );
+        assert_eq!(
+            parse_error(&["pick", "/a", "--count", "-2"]),
+            "`--count` must be a positive number"
+        );
+        assert_eq!(
+            parse_error(&["pick", "/a", "--seed", "x"]),
+            "`--seed` must be a number"
+        );
+        assert_eq!(
+            parse_error(&["pick", "/a", "--all"]),
+            "unknown option `--all`"
+        );
+        assert!(parse_error(&["favorites", "add"]).starts_with("expected `favorites list`"));
+        assert!(parse_error(&["favorites", "list", "/a"]).starts_with("expected `favorites list`"));
+        assert_eq!(
+            parse_error(&["shuffle-everything"]),
+            "unknown command `shuffle-everything`"
+        );
+        // an empty error prints the usage
+        assert_eq!(parse_error(&["--help"]), "");
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(json_string("plain"), r#""plain""#);
+        assert_eq!(json_string(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(json_string(r"C:\dir"), r#""C:\\dir""#);
+        assert_eq!(json_string("a\nb\tc"), r#""a\nb\tc""#);
+        assert_eq!(json_string("bell\u{7}\u{1f}"), r#""bell\u0007\u001f""#);
+        assert_eq!(json_string("été 🌄"), r#""été 🌄""#);
+    }
+}
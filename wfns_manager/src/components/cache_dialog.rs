@@ -0,0 +1,20 @@
+use adw::prelude::*;
+use relm4::adw;
+
+/// alert dialog showing the size of the thumbnails cache, `on_clear` is called if the user chose to clear it
+pub fn show_cache_dialog(size: &str, max_size: &str, on_clear: impl Fn() + 'static) {
+    let dialog = adw::AlertDialog::builder()
+        .heading("Thumbnail Cache")
+        .body(format!(
+            "The videos thumbnails take {size} on your disk, the least recently viewed ones are removed past {max_size}."
+        ))
+        .build();
+    dialog.add_response("close", "Close");
+    dialog.add_response("clear", "Clear Cache");
+    dialog.set_response_appearance("clear", adw::ResponseAppearance::Destructive);
+    dialog.connect_response(Some("clear"), move |_, _| on_clear());
+
+    if let Some(window) = relm4::main_application().active_window() {
+        dialog.present(&window);
+    }
+}
@@ -0,0 +1,28 @@
+use adw::prelude::*;
+use relm4::{adw, gtk};
+
+/// alert dialog explaining an error, its technical details are hidden in an expander
+pub fn show_error_dialog(message: &str, details: &str) {
+    let details_label = gtk::Label::builder()
+        .label(details)
+        .selectable(true)
+        .wrap(true)
+        .xalign(0.0)
+        .build();
+    details_label.add_css_class("monospace");
+    let expander = gtk::Expander::builder()
+        .label("Details")
+        .child(&details_label)
+        .build();
+
+    let dialog = adw::AlertDialog::builder()
+        .heading("Something went wrong")
+        .body(message)
+        .extra_child(&expander)
+        .build();
+    dialog.add_response("close", "Close");
+
+    if let Some(window) = relm4::main_application().active_window() {
+        dialog.present(&window);
+    }
+}
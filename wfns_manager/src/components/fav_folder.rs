@@ -1,18 +1,38 @@
 use adw::prelude::*;
 use relm4::{
-    adw, gtk,
+    adw,
+    gtk::{self, gdk},
     prelude::{DynamicIndex, FactoryComponent},
     FactorySender,
 };
 
+use crate::app::preferences::{AppMode, Favorite};
+
+/// the modes of the dropdown, `None` opens the folder in the last used mode
+const FAV_MODES: [Option<AppMode>; 5] = [
+    None,
+    Some(AppMode::SubFolders),
+    Some(AppMode::Images),
+    Some(AppMode::Videos),
+    Some(AppMode::All),
+];
+
 #[derive(Clone)]
 pub struct FavFolderModel {
-    pub path: String,
+    pub fav: Favorite,
+    missing: bool,
 }
 
+/// the favorites are identified by their path
 #[derive(Debug)]
 pub enum FavFolderOutput {
     ChoseFavFolder(String),
+    Remove(String),
+    /// an empty alias shows the folder's name again
+    Rename(String, String),
+    SetAppMode(String, Option<AppMode>),
+    /// dropped on the favorite at this position
+    Move(String, usize),
 }
 
 #[relm4::factory(pub)]
@@ -20,28 +40,121 @@ impl FactoryComponent for FavFolderModel {
     type ParentWidget = adw::ExpanderRow;
     type Input = ();
     type Output = FavFolderOutput;
-    type Init = String;
+    type Init = Favorite;
     type CommandOutput = ();
 
     view! {
         #[root]
         adw::ActionRow {
-            set_title: &self.path,
+            set_title: &self.fav.name(),
+            set_subtitle: &self.fav.path,
+            set_use_markup: false, // paths may contain markup characters
+            set_title_lines: 1,
+            set_subtitle_lines: 1,
             set_hexpand: true,
+            add_controller: self.drag_source(),
+            add_controller: Self::drop_target(index, &sender),
+
+            add_prefix = &gtk::Image {
+                set_icon_name: Some("list-drag-handle-symbolic"),
+                set_tooltip_text: Some("Drag to reorder"),
+                add_css_class: "dim-label",
+            },
+            add_prefix = &gtk::Image {
+                set_icon_name: Some("dialog-warning-symbolic"),
+                set_tooltip_text: Some("This folder doesn't exist anymore, it may have been moved or its drive unmounted"),
+                add_css_class: "warning",
+                set_visible: self.missing,
+            },
+
+            add_suffix: &self.mode_dropdown(&sender),
+            add_suffix = &gtk::MenuButton {
+                set_icon_name: "document-edit-symbolic",
+                set_tooltip_text: Some("Rename"),
+                set_valign: gtk::Align::Center,
+                add_css_class: "flat",
+                #[wrap(Some)]
+                set_popover = &gtk::Popover {
+                    gtk::Entry {
+                        set_text: self.fav.alias.as_deref().unwrap_or_default(),
+                        set_placeholder_text: Some("Name shown instead of the folder's"),
+                        connect_activate[sender, path = self.fav.path.clone()] => move |entry| {
+                            let _ = sender.output(FavFolderOutput::Rename(path.clone(), entry.text().to_string()));
+                        },
+                    },
+                },
+            },
+            add_suffix = &gtk::Button {
+                set_icon_name: "user-trash-symbolic",
+                set_tooltip_text: Some("Remove from the favorites"),
+                set_valign: gtk::Align::Center,
+                add_css_class: "flat",
+                connect_clicked[sender, path = self.fav.path.clone()] => move |_| {
+                    let _ = sender.output(FavFolderOutput::Remove(path.clone()));
+                },
+            },
             add_suffix = &gtk::Button {
                 set_margin_start: 5,
+                set_valign: gtk::Align::Center,
+                set_sensitive: !self.missing,
                 adw::ButtonContent {
                     set_icon_name: "check-mini",
                 },
-                set_widget_name: &self.path, // just for the path to be easily collected bellow
-                connect_clicked[sender] => move |b| {
-                    let _ = sender.output(FavFolderOutput::ChoseFavFolder(b.widget_name().to_string())); // can't move self..
+                connect_clicked[sender, path = self.fav.path.clone()] => move |_| {
+                    let _ = sender.output(FavFolderOutput::ChoseFavFolder(path.clone()));
                 },
             },
         }
     }
 
-    fn init_model(path: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
-        Self { path }
+    fn init_model(fav: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        Self {
+            missing: fav.is_missing(),
+            fav,
+        }
+    }
+}
+
+impl FavFolderModel {
+    fn mode_dropdown(&self, sender: &FactorySender<Self>) -> gtk::DropDown {
+        let dropdown =
+            gtk::DropDown::from_strings(&["Last mode", "Subfolders", "Images", "Videos", "All"]);
+        dropdown.set_valign(gtk::Align::Center);
+        dropdown.set_tooltip_text(Some("Mode the folder is opened in"));
+        let selected = FAV_MODES.iter().position(|m| *m == self.fav.app_mode);
+        dropdown.set_selected(selected.unwrap_or_default() as u32);
+
+        let (sender, path) = (sender.clone(), self.fav.path.clone());
+        dropdown.connect_selected_notify(move |dropdown| {
+            let app_mode = FAV_MODES
+                .get(dropdown.selected() as usize)
+                .cloned()
+                .flatten();
+            let _ = sender.output(FavFolderOutput::SetAppMode(path.clone(), app_mode));
+        });
+        dropdown
+    }
+
+    /// the row carries the path of its favorite when dragged
+    fn drag_source(&self) -> gtk::DragSource {
+        let drag_source = gtk::DragSource::new();
+        drag_source.set_actions(gdk::DragAction::MOVE);
+        drag_source.set_content(Some(&gdk::ContentProvider::for_value(
+            &self.fav.path.to_value(),
+        )));
+        drag_source
+    }
+
+    fn drop_target(index: &DynamicIndex, sender: &FactorySender<Self>) -> gtk::DropTarget {
+        let drop_target = gtk::DropTarget::new(String::static_type(), gdk::DragAction::MOVE);
+        let (index, sender) = (index.clone(), sender.clone());
+        drop_target.connect_drop(move |_, value, _, _| match value.get::<String>() {
+            Ok(path) => {
+                let _ = sender.output(FavFolderOutput::Move(path, index.current_index()));
+                true
+            }
+            Err(_) => false,
+        });
+        drop_target
     }
 }
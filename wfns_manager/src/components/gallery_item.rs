@@ -0,0 +1,133 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::prelude::*;
+use relm4::{
+    gtk::{self, gdk, gdk_pixbuf::Pixbuf, gio, glib},
+    typed_view::grid::RelmGridItem,
+    RelmWidgetExt,
+};
+
+use crate::app::{
+    gallery::{GalleryEntry, GALLERY_THUMBNAIL_SIZE},
+    thumbnailer::FALLBACK_ICON,
+};
+
+/// thumbnail of a cell, loaded the first time the cell is shown
+#[derive(Debug, Clone)]
+enum CellThumbnail {
+    Loading,
+    /// `None` if nothing could render the item
+    Loaded(Option<gdk::Texture>),
+}
+
+pub struct GalleryItem {
+    pub entry: GalleryEntry,
+    thumbnail: Rc<RefCell<Option<CellThumbnail>>>,
+}
+
+impl GalleryItem {
+    pub fn new(entry: GalleryEntry) -> Self {
+        Self {
+            entry,
+            thumbnail: Rc::default(),
+        }
+    }
+}
+
+pub struct GalleryItemWidgets {
+    image: gtk::Image,
+    label: gtk::Label,
+    /// key of the item shown by the cell, the cells are recycled while scrolling
+    bound: Rc<RefCell<Option<String>>>,
+}
+
+impl RelmGridItem for GalleryItem {
+    type Root = gtk::Box;
+    type Widgets = GalleryItemWidgets;
+
+    fn setup(_item: &gtk::ListItem) -> (gtk::Box, GalleryItemWidgets) {
+        relm4::view! {
+            root = gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 5,
+                set_margin_all: 5,
+
+                #[name = "image"]
+                gtk::Image {
+                    set_pixel_size: GALLERY_THUMBNAIL_SIZE,
+                    set_size_request: (GALLERY_THUMBNAIL_SIZE, GALLERY_THUMBNAIL_SIZE),
+                },
+                #[name = "label"]
+                gtk::Label {
+                    set_ellipsize: gtk::pango::EllipsizeMode::End,
+                    set_max_width_chars: 20,
+                },
+            }
+        }
+
+        let widgets = GalleryItemWidgets {
+            image,
+            label,
+            bound: Rc::default(),
+        };
+        (root, widgets)
+    }
+
+    fn bind(&mut self, widgets: &mut Self::Widgets, root: &mut Self::Root) {
+        widgets.label.set_label(&self.entry.name);
+        root.set_tooltip_text(Some(&self.entry.key));
+        *widgets.bound.borrow_mut() = Some(self.entry.key.clone());
+
+        let thumbnail = self.thumbnail.borrow().clone();
+        match thumbnail {
+            Some(t) => show_thumbnail(&widgets.image, &t, self.entry.video),
+            None => {
+                *self.thumbnail.borrow_mut() = Some(CellThumbnail::Loading);
+                show_thumbnail(&widgets.image, &CellThumbnail::Loading, self.entry.video);
+                self.load_thumbnail(widgets);
+            }
+        }
+    }
+
+    fn unbind(&mut self, widgets: &mut Self::Widgets, _root: &mut Self::Root) {
+        *widgets.bound.borrow_mut() = None;
+        widgets.image.clear();
+    }
+}
+
+impl GalleryItem {
+    /// decode (or generate, for a video) the thumbnail in the background, then show it if the cell still
+    /// shows this item
+    fn load_thumbnail(&self, widgets: &GalleryItemWidgets) {
+        let entry = self.entry.clone();
+        let handle = gio::spawn_blocking(move || {
+            let path = entry.thumbnail_path()?;
+            let size = GALLERY_THUMBNAIL_SIZE;
+            let pixbuf = Pixbuf::from_file_at_scale(path, size, size, true).ok()?;
+            Some(gdk::Texture::for_pixbuf(&pixbuf))
+        });
+
+        let (thumbnail, image, bound) = (
+            self.thumbnail.clone(),
+            widgets.image.clone(),
+            widgets.bound.clone(),
+        );
+        let (key, video) = (self.entry.key.clone(), self.entry.video);
+        glib::spawn_future_local(async move {
+            let loaded = CellThumbnail::Loaded(handle.await.ok().flatten());
+            if bound.borrow().as_ref() == Some(&key) {
+                show_thumbnail(&image, &loaded, video);
+            }
+            *thumbnail.borrow_mut() = Some(loaded);
+        });
+    }
+}
+
+fn show_thumbnail(image: &gtk::Image, thumbnail: &CellThumbnail, video: bool) {
+    match thumbnail {
+        CellThumbnail::Loading => image.set_icon_name(Some("image-loading-symbolic")),
+        CellThumbnail::Loaded(Some(texture)) => image.set_from_paintable(Some(texture)),
+        CellThumbnail::Loaded(None) if video => image.set_icon_name(Some(FALLBACK_ICON)),
+        CellThumbnail::Loaded(None) => image.set_icon_name(Some("image-missing-symbolic")),
+    }
+}
@@ -10,6 +10,8 @@ pub struct HeaderModel {
     bookmarked: bool,
     show_bookmark_btn: bool,
     app_mode: AppMode,
+    /// rating of the current item, the stars are shown along with the bookmark button
+    rating: Option<u8>,
 }
 
 #[derive(Debug)]
@@ -19,20 +21,26 @@ pub enum HeaderInput {
     SetBookmark(bool),
     ChangeAppMode,
     ChangeToKnownAppMode(AppMode),
+    SetRating(Option<u8>),
+    /// a star was clicked, clicking the star of the current rating clears it
+    ClickStar(u8),
 }
 
 #[derive(Debug)]
 pub enum HeaderOutput {
     About,
     Shortcuts,
+    ThumbnailCache,
     NewDir,
     SetBookmarked(bool),
     ChangeAppMode(AppMode),
+    SetRating(Option<u8>),
 }
 
 relm4::new_action_group!(HeaderMenuActionGroup, "win");
 relm4::new_stateless_action!(OpenAbout, HeaderMenuActionGroup, "about");
 relm4::new_stateless_action!(OpenShortcuts, HeaderMenuActionGroup, "shortcuts");
+relm4::new_stateless_action!(OpenThumbnailCache, HeaderMenuActionGroup, "thumbnail_cache");
 
 #[relm4::component(pub)]
 impl SimpleComponent for HeaderModel {
@@ -59,6 +67,7 @@ impl SimpleComponent for HeaderModel {
                         AppMode::SubFolders => "folder-drag-accept-symbolic",
                         AppMode::Images => "image-x-generic-symbolic",
                         AppMode::Videos => "folder-videos-symbolic",
+                        AppMode::All => "view-grid-symbolic",
                     },
                 },
                 connect_clicked => HeaderInput::ChangeAppMode,
@@ -82,6 +91,43 @@ impl SimpleComponent for HeaderModel {
                 #[wrap(Some)]
                 set_popover = &gtk::PopoverMenu::from_model(Some(&main_menu)) {}
             },
+            pack_end = &gtk::Box {
+                set_margin_end: 5,
+                add_css_class: "linked",
+                #[watch]
+                set_visible: model.show_bookmark_btn,
+
+                gtk::Button {
+                    add_css_class: "flat",
+                    #[watch]
+                    set_icon_name: model.star_icon(1),
+                    connect_clicked => HeaderInput::ClickStar(1),
+                },
+                gtk::Button {
+                    add_css_class: "flat",
+                    #[watch]
+                    set_icon_name: model.star_icon(2),
+                    connect_clicked => HeaderInput::ClickStar(2),
+                },
+                gtk::Button {
+                    add_css_class: "flat",
+                    #[watch]
+                    set_icon_name: model.star_icon(3),
+                    connect_clicked => HeaderInput::ClickStar(3),
+                },
+                gtk::Button {
+                    add_css_class: "flat",
+                    #[watch]
+                    set_icon_name: model.star_icon(4),
+                    connect_clicked => HeaderInput::ClickStar(4),
+                },
+                gtk::Button {
+                    add_css_class: "flat",
+                    #[watch]
+                    set_icon_name: model.star_icon(5),
+                    connect_clicked => HeaderInput::ClickStar(5),
+                },
+            },
         }
     }
 
@@ -89,6 +135,7 @@ impl SimpleComponent for HeaderModel {
         main_menu: {
             "About WFNS" => OpenAbout,
             "Shortcuts" => OpenShortcuts,
+            "Thumbnail Cache" => OpenThumbnailCache,
         }
     }
 
@@ -101,6 +148,7 @@ impl SimpleComponent for HeaderModel {
             bookmarked,
             show_bookmark_btn: show,
             app_mode: AppMode::default(),
+            rating: None,
         };
         let widgets = view_output!();
 
@@ -117,9 +165,16 @@ impl SimpleComponent for HeaderModel {
                     let _ = shortcuts_sender.output(HeaderOutput::Shortcuts);
                 });
 
+            let cache_sender = sender.clone();
+            let action_cache: RelmAction<OpenThumbnailCache> =
+                RelmAction::new_stateless(move |_| {
+                    let _ = cache_sender.output(HeaderOutput::ThumbnailCache);
+                });
+
             let mut menu_group = RelmActionGroup::<HeaderMenuActionGroup>::new();
             menu_group.add_action(action_about);
             menu_group.add_action(action_shortcuts);
+            menu_group.add_action(action_cache);
             menu_group.register_for_widget(&widgets.header);
         }
 
@@ -142,11 +197,29 @@ impl SimpleComponent for HeaderModel {
                 self.app_mode = match self.app_mode {
                     AppMode::SubFolders => AppMode::Images,
                     AppMode::Images => AppMode::Videos,
-                    AppMode::Videos => AppMode::SubFolders,
+                    AppMode::Videos => AppMode::All,
+                    AppMode::All => AppMode::SubFolders,
                 };
                 let _ = sender.output(HeaderOutput::ChangeAppMode(self.app_mode.clone()));
             }
             HeaderInput::SetBookmark(b) => self.bookmarked = b,
+            HeaderInput::SetRating(rating) => self.rating = rating,
+            HeaderInput::ClickStar(star) => {
+                self.rating = match self.rating == Some(star) {
+                    true => None,
+                    false => Some(star),
+                };
+                let _ = sender.output(HeaderOutput::SetRating(self.rating));
+            }
+        }
+    }
+}
+
+impl HeaderModel {
+    fn star_icon(&self, star: u8) -> &'static str {
+        match self.rating.is_some_and(|r| r >= star) {
+            true => "starred-symbolic",
+            false => "non-starred-symbolic",
         }
     }
 }
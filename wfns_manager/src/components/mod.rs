@@ -1,4 +1,8 @@
 pub mod about;
+pub mod cache_dialog;
+pub mod error_dialog;
 pub mod fav_folder;
+pub mod gallery_item;
 pub mod header;
+pub mod search_result;
 pub mod shortcuts;
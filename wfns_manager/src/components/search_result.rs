@@ -0,0 +1,73 @@
+use adw::prelude::*;
+use relm4::{
+    adw,
+    gtk::{self, gdk, gdk_pixbuf::Pixbuf, gio, glib},
+    prelude::{DynamicIndex, FactoryComponent},
+    FactorySender,
+};
+
+use crate::app::{backend::get_image_thumbnail, search::SearchHit};
+
+const THUMBNAIL_SIZE: i32 = 48;
+
+pub struct SearchResultModel {
+    pub hit: SearchHit,
+}
+
+#[derive(Debug)]
+pub enum SearchResultOutput {
+    Chose(String),
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for SearchResultModel {
+    type ParentWidget = gtk::ListBox;
+    type Input = ();
+    type Output = SearchResultOutput;
+    type Init = SearchHit;
+    type CommandOutput = ();
+
+    view! {
+        #[root]
+        adw::ActionRow {
+            set_title: &self.hit.name,
+            set_subtitle: &self.hit.key,
+            set_title_lines: 1,
+            set_subtitle_lines: 1,
+            set_activatable: true,
+            add_prefix: &self.thumbnail_image(),
+            connect_activated[sender, key = self.hit.key.clone()] => move |_| {
+                let _ = sender.output(SearchResultOutput::Chose(key.clone()));
+            },
+        }
+    }
+
+    fn init_model(hit: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        Self { hit }
+    }
+}
+
+impl SearchResultModel {
+    /// the thumbnail is decoded at its small size in the background, the results change at each keystroke
+    fn thumbnail_image(&self) -> gtk::Image {
+        let image = gtk::Image::from_icon_name("image-loading-symbolic");
+        image.set_pixel_size(THUMBNAIL_SIZE);
+        image.set_size_request(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+
+        let thumbnail = self.hit.thumbnail.clone();
+        let handle = gio::spawn_blocking(move || {
+            let path = get_image_thumbnail(&thumbnail?, THUMBNAIL_SIZE as u32);
+            let pixbuf =
+                Pixbuf::from_file_at_scale(path, THUMBNAIL_SIZE, THUMBNAIL_SIZE, true).ok()?;
+            Some(gdk::Texture::for_pixbuf(&pixbuf))
+        });
+        let loaded_image = image.clone();
+        glib::spawn_future_local(async move {
+            match handle.await.ok().flatten() {
+                Some(texture) => loaded_image.set_from_paintable(Some(&texture)),
+                None => loaded_image.set_icon_name(Some("image-missing-symbolic")),
+            }
+        });
+        image
+    }
+}
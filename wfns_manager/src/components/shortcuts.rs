@@ -35,11 +35,36 @@ pub fn build_shortcuts_window(root: &gtk::Window) -> gtk::ShortcutsWindow {
         .accelerator("Left")
         .title("Rollback to last subfolder")
         .build();
+    let search = gtk::ShortcutsShortcut::builder()
+        .accelerator("<ctrl>f")
+        .title("Search an item of the folder")
+        .build();
+    let gallery = gtk::ShortcutsShortcut::builder()
+        .accelerator("<ctrl>g")
+        .title("Show or leave the gallery of the folder")
+        .build();
+    let browser = gtk::ShortcutsShortcut::builder()
+        .accelerator("<ctrl>b")
+        .title("Browse the images of the subfolder inside of the app")
+        .build();
+    let browser_paging = gtk::ShortcutsShortcut::builder()
+        .accelerator("Left Right Page_Up Page_Down Home End")
+        .title("Flip through the images of the subfolder")
+        .build();
+    let rate = gtk::ShortcutsShortcut::builder()
+        .accelerator("1...5")
+        .title("Rate the current item (0 to clear the rating)")
+        .build();
     group.append(&open_new);
     group.append(&open_sf_img);
     group.append(&open_sf);
     group.append(&next);
     group.append(&prev);
+    group.append(&search);
+    group.append(&gallery);
+    group.append(&browser);
+    group.append(&browser_paging);
+    group.append(&rate);
 
     section.append(&group);
     shortcuts_window.set_child(Some(&section));
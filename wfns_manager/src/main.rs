@@ -1,26 +1,64 @@
 mod app;
+mod cli;
 mod components;
 mod config;
 
-use app::{preferences::AppPreferences, AppModel};
+use std::process::ExitCode;
+
+use app::{cover, preferences::AppPreferences, AppInput, AppModel};
 use config::APP_ID;
 use relm4::{
-    gtk::{self, gdk, gio, glib},
-    RelmApp,
+    gtk::{self, gdk, gio, glib, prelude::*},
+    MessageBroker, RelmApp,
 };
 
-fn main() {
+/// lets the folders opened from outside of the app reach it
+static APP_BROKER: MessageBroker<AppInput> = MessageBroker::new();
+
+fn main() -> ExitCode {
+    // scripts and launchers use the command line without the window
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match cli::parse(&args) {
+        Ok(Some(command)) => return cli::run(command),
+        Ok(None) => {}
+        Err(error) => return cli::usage(&error),
+    }
+
     glib::set_application_name("WFNS Manager");
 
     // create app
-    let app = RelmApp::new(APP_ID);
+    let app = RelmApp::new(APP_ID).with_broker(&APP_BROKER);
+
+    // folders passed on the command line or from the file manager (paths or URIs), when the app is already
+    // running they're forwarded to it
+    let gtk_app = relm4::main_application();
+    gtk_app.set_flags(gio::ApplicationFlags::HANDLES_OPEN);
+    gtk_app.connect_open(|gtk_app, files, _| {
+        gtk_app.activate();
+        let folders = files
+            .iter()
+            .filter_map(|f| f.path())
+            .filter_map(|p| p.to_str().map(|s| s.to_string()))
+            .collect::<Vec<_>>();
+        if !folders.is_empty() {
+            APP_BROKER.send(AppInput::AddFolders(folders));
+        }
+    });
 
     // init icons
     initialize_custom_icons();
     gtk::Window::set_default_icon_name("logo");
 
+    // the user finds where to customize the covers, but they are found without it
+    let _ = cover::write_default_patterns();
+
     // launch app
-    app.run::<AppModel>(AppPreferences::load());
+    let (prefs, load_error) = match AppPreferences::load() {
+        Ok(prefs) => (prefs, None),
+        Err(err) => (AppPreferences::fallback(&err), Some(err)),
+    };
+    app.run::<AppModel>((prefs, load_error));
+    ExitCode::SUCCESS
 }
 
 fn initialize_custom_icons() {